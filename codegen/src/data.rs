@@ -10,13 +10,6 @@ pub struct Api {
     pub events: IndexMap<String, Vec<String>>,
 }
 
-#[derive(Deserialize, Debug)]
-#[serde(deny_unknown_fields)]
-pub struct Binding {
-    #[serde(default)]
-    pub valid_elements: Vec<String>,
-}
-
 impl FromIterator<Api> for Api {
     fn from_iter<T: IntoIterator<Item = Api>>(iter: T) -> Self {
         iter.into_iter()
@@ -13,6 +13,12 @@ pub fn stardom_macro_include<W: Write>(mut w: W, api: &Api) -> io::Result<()> {
     }
     writeln!(w, "];")?;
 
+    writeln!(w, "\nconst VOID: &[&str] = &[")?;
+    for element in &api.void {
+        writeln!(w, "   \"{}\",", element)?;
+    }
+    writeln!(w, "];")?;
+
     writeln!(w, "\nconst ATTRS: &[&str] = &[")?;
     for attr in &api.attributes {
         writeln!(w, "   \"{}\",", attr)?;
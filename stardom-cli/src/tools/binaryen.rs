@@ -75,7 +75,7 @@ pub enum OptLevel {
 }
 
 impl OptLevel {
-    pub fn to_char(&self) -> char {
+    pub fn to_char(self) -> char {
         match self {
             Self::Zero => '0',
             Self::One => '1',
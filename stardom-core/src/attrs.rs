@@ -1,7 +1,23 @@
 use std::borrow::Cow;
 
+use stardom_reactive::{Input, Memo, Signal};
+
 use crate::node::Node;
 
+/// Converts a value into the value of an attribute, or `None` to remove the attribute.
+///
+/// `bool` sets the attribute to an empty value when `true`, and removes it otherwise, as boolean attributes like
+/// `disabled` are toggled by their presence. Enums can derive this trait, mapping each variant to its name in
+/// kebab case, or to the string given by `#[attr("...")]`:
+///
+/// ```ignore
+/// #[derive(Clone, Copy, IntoAttr)]
+/// enum Kind {
+///     Button,
+///     #[attr("submit")]
+///     Send,
+/// }
+/// ```
 pub trait IntoAttr<'a>: Sized {
     fn into_attr(self) -> Option<Cow<'a, str>>;
 
@@ -21,7 +37,7 @@ impl<'a> IntoAttr<'a> for &'a str {
 }
 
 impl<'a> IntoAttr<'a> for Cow<'a, str> {
-    fn into_attr(self) -> Option<Cow<'a, str>> {
+    fn into_attr(self) -> Option<Self> {
         Some(self)
     }
 }
@@ -38,26 +54,191 @@ impl<'a> IntoAttr<'a> for &'a String {
     }
 }
 
-impl<'a> IntoAttr<'a> for Option<&'a str> {
+impl<'a, T> IntoAttr<'a> for Option<T>
+where
+    T: IntoAttr<'a>,
+{
     fn into_attr(self) -> Option<Cow<'a, str>> {
-        self.map(Cow::Borrowed)
+        self.and_then(IntoAttr::into_attr)
     }
 }
 
-impl<'a> IntoAttr<'a> for Option<Cow<'a, str>> {
-    fn into_attr(self) -> Self {
-        self
+impl<'a> IntoAttr<'a> for bool {
+    fn into_attr(self) -> Option<Cow<'a, str>> {
+        self.then_some(Cow::Borrowed(""))
     }
 }
 
-impl<'a> IntoAttr<'a> for Option<String> {
+macro_rules! impl_display {
+    ($($ty:ty),*) => {
+        $(
+            impl<'a> IntoAttr<'a> for $ty {
+                fn into_attr(self) -> Option<Cow<'a, str>> {
+                    Some(Cow::Owned(self.to_string()))
+                }
+            }
+        )*
+    };
+}
+
+impl_display!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64, char);
+
+impl<'a, T> IntoAttr<'a> for Signal<T>
+where
+    T: Clone + IntoAttr<'a> + 'static,
+{
     fn into_attr(self) -> Option<Cow<'a, str>> {
-        self.map(Cow::Owned)
+        self.cloned().into_attr()
     }
 }
 
-impl<'a> IntoAttr<'a> for Option<&'a String> {
+impl<'a, T> IntoAttr<'a> for Memo<T>
+where
+    T: Clone + IntoAttr<'a> + 'static,
+{
     fn into_attr(self) -> Option<Cow<'a, str>> {
-        self.map(|s| Cow::Borrowed(s.as_str()))
+        self.cloned().into_attr()
+    }
+}
+
+/// Returns `class` with the class `name` added or removed, as `classList.toggle` does.
+pub(crate) fn toggle_class(class: &str, name: &str, enabled: bool) -> String {
+    let mut classes = class.split_ascii_whitespace().collect::<Vec<_>>();
+    if !enabled {
+        classes.retain(|class| *class != name);
+    } else if !classes.contains(&name) {
+        classes.push(name);
+    }
+    classes.join(" ")
+}
+
+/// Returns `style` with the declaration of `property` set to `value`, or removed if `value` is `None`, as
+/// `style.setProperty` and `style.removeProperty` do.
+pub(crate) fn set_style_property(style: &str, property: &str, value: Option<&str>) -> String {
+    let mut declarations = split_declarations(style)
+        .into_iter()
+        .filter_map(|declaration| {
+            let (property, value) = declaration.split_once(':')?;
+            Some((property.trim(), value.trim()))
+        })
+        .collect::<Vec<_>>();
+    match value {
+        Some(value) => match declarations.iter_mut().find(|(name, _)| *name == property) {
+            Some(declaration) => declaration.1 = value,
+            None => declarations.push((property, value)),
+        },
+        None => declarations.retain(|(name, _)| *name != property),
+    }
+
+    declarations
+        .iter()
+        .map(|(property, value)| format!("{property}: {value};"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Splits an inline style at the semicolons which are neither within a string nor within parentheses, such as
+/// the one of `url("data:image/png;base64,...")`.
+fn split_declarations(style: &str) -> Vec<&str> {
+    let mut declarations = vec![];
+    let mut start = 0;
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in style.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match (c, quote) {
+            ('\\', _) => escaped = true,
+            (c, Some(open)) if c == open => quote = None,
+            (_, Some(_)) => {}
+            ('"' | '\'', None) => quote = Some(c),
+            ('(', None) => depth += 1,
+            (')', None) => depth = depth.saturating_sub(1),
+            (';', None) if depth == 0 => {
+                declarations.push(&style[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    declarations.push(&style[start..]);
+    declarations
+}
+
+#[cfg(test)]
+mod tests {
+    use stardom_reactive::{run, signal, Output};
+
+    use super::*;
+
+    #[derive(stardom_macros::IntoAttr)]
+    enum Kind {
+        ResetAll,
+        #[attr("submit")]
+        Send,
+    }
+
+    #[test]
+    fn directives_merge_into_attributes() {
+        run(|dispose| {
+            let node = Node::element("div".into());
+            node.set_attr("class".into(), "card active".into());
+            node.set_attr("style".into(), "color: red".into());
+            node.set_class("active", false);
+            node.set_class("open", true);
+            node.set_style("margin", Some("0".into()));
+            node.set_style("color", Some("blue".into()));
+
+            let attrs = node.kind().element_attrs();
+            assert_eq!(attrs["class"], "card open");
+            assert_eq!(attrs["style"], "color: blue; margin: 0;");
+
+            // semicolons within strings and parentheses are part of the value
+            let image = "url(\"data:image/png;base64,AA==\")";
+            node.set_attr("style".into(), format!("background: {image}; content: ';'"));
+            assert_eq!(
+                node.kind().element_attrs()["style"],
+                format!("background: {image}; content: ';'; margin: 0; color: blue;")
+            );
+
+            // the attributes themselves only hold the values they were set to
+            node.set_attr("class".into(), "panel".into());
+            assert_eq!(node.kind().element_attrs()["class"], "panel open");
+
+            dispose();
+        });
+    }
+
+    #[test]
+    fn typed_values_set_attributes() {
+        run(|dispose| {
+            let node = Node::element("input".into());
+            let disabled = signal(true);
+
+            2u8.set_attr(&node, "size".into());
+            0.5f64.set_attr(&node, "step".into());
+            disabled.set_attr(&node, "disabled".into());
+            Some('x').set_attr(&node, "accesskey".into());
+            assert_eq!(node.attr("size").as_deref(), Some("2"));
+            assert_eq!(node.attr("step").as_deref(), Some("0.5"));
+            assert_eq!(node.attr("disabled").as_deref(), Some(""));
+            assert_eq!(node.attr("accesskey").as_deref(), Some("x"));
+
+            disabled.set(false);
+            disabled.set_attr(&node, "disabled".into());
+            None::<u8>.set_attr(&node, "size".into());
+            assert_eq!(node.attr("disabled"), None);
+            assert_eq!(node.attr("size"), None);
+
+            Kind::ResetAll.set_attr(&node, "type".into());
+            assert_eq!(node.attr("type").as_deref(), Some("reset-all"));
+            Kind::Send.set_attr(&node, "type".into());
+            assert_eq!(node.attr("type").as_deref(), Some("submit"));
+
+            dispose();
+        });
     }
 }
@@ -2,12 +2,15 @@ use std::{
     any::{self, Any, TypeId},
     cell::{Cell, RefCell},
     collections::HashMap,
-    mem, thread_local,
+    mem,
+    rc::Rc,
+    thread_local,
 };
 
 use crate::{
     env::is_browser,
     node::{Node, NodeKind},
+    state,
 };
 
 thread_local! {
@@ -18,8 +21,11 @@ thread_local! {
 pub(crate) struct Component {
     pub(crate) frozen: Cell<bool>,
     pub(crate) mounted: Cell<bool>,
+    /// Whether this is the frame of content created by [`Contexts::create`], which is rendered without markers.
+    pub(crate) transparent: bool,
+    position: Position,
 
-    contexts: HashMap<TypeId, Box<dyn Any>>,
+    contexts: HashMap<TypeId, Rc<dyn Any>>,
     pub(crate) on_mount: RefCell<Vec<Box<dyn FnOnce()>>>,
     pub(crate) on_unmount: RefCell<Vec<Box<dyn FnOnce()>>>,
 }
@@ -29,7 +35,18 @@ impl Component {
     where
         F: FnOnce() -> Node,
     {
-        STACK.with_borrow_mut(|stack| stack.push(Self::default()));
+        let mut component = Self::default();
+        component.position = Position::current().child();
+        component.wrap(f)
+    }
+
+    /// Creates the node returned by `f` with this component on top of the stack, and wraps it in a component
+    /// node.
+    fn wrap<F>(self, f: F) -> Node
+    where
+        F: FnOnce() -> Node,
+    {
+        STACK.with_borrow_mut(|stack| stack.push(self));
         let content = f();
         let component = STACK.with_borrow_mut(|stack| stack.pop().unwrap());
 
@@ -38,6 +55,14 @@ impl Component {
         node
     }
 
+    /// Returns the context of type `T` registered by this component.
+    pub(crate) fn context<T: Clone + 'static>(&self) -> Option<T> {
+        self.contexts
+            .get(&TypeId::of::<T>())?
+            .downcast_ref()
+            .cloned()
+    }
+
     pub(crate) fn on_mount(&self) {
         if !self.mounted.get() {
             self.mounted.set(true);
@@ -48,6 +73,86 @@ impl Component {
     }
 }
 
+/// A component's position in the component tree, which keys the state it [transfers](crate::state::transfer).
+///
+/// Positions are derived from the order components are created in, so they are the same on the server and the
+/// client as long as both create the same tree.
+#[derive(Clone, Default)]
+pub(crate) struct Position {
+    key: Rc<str>,
+    children: Rc<Cell<usize>>,
+    states: Rc<Cell<usize>>,
+}
+
+impl Position {
+    /// Returns the position of the component being created, or the root position outside of components.
+    pub(crate) fn current() -> Self {
+        STACK
+            .with_borrow(|stack| stack.last().map(|component| component.position.clone()))
+            .unwrap_or_else(state::root)
+    }
+
+    fn child(&self) -> Self {
+        let index = next(&self.children);
+        Self {
+            key: format!("{}.{index}", self.key).into(),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the key of the next state transferred at this position.
+    pub(crate) fn next_state(&self) -> String {
+        format!("{}:{}", self.key, next(&self.states))
+    }
+}
+
+fn next(counter: &Cell<usize>) -> usize {
+    let value = counter.get();
+    counter.set(value + 1);
+    value
+}
+
+/// The contexts visible at some point during component creation.
+///
+/// Nodes created after their parent component (such as within an effect) no longer have access to the component
+/// stack, so the contexts are captured beforehand and provided again when the nodes are created.
+#[derive(Clone, Default)]
+pub(crate) struct Contexts {
+    contexts: HashMap<TypeId, Rc<dyn Any>>,
+    position: Position,
+}
+
+impl Contexts {
+    pub(crate) fn capture() -> Self {
+        let contexts = STACK.with_borrow(|stack| {
+            stack
+                .iter()
+                .flat_map(|component| component.contexts.clone())
+                .collect()
+        });
+        Self {
+            contexts,
+            position: Position::current(),
+        }
+    }
+
+    /// Creates the node returned by `f` within a frame of its own providing these contexts.
+    ///
+    /// The frame is a [transparent](Component::transparent) component wrapping the node, so that hooks like
+    /// [`on_unmount`] registered by `f` run when that node is mounted and dropped, such as a row of
+    /// [`each`](crate::flow::each) being added and removed.
+    pub(crate) fn create<F>(&self, f: F) -> Node
+    where
+        F: FnOnce() -> Node,
+    {
+        let mut component = Component::default();
+        component.transparent = true;
+        component.contexts.clone_from(&self.contexts);
+        component.position = self.position.clone();
+        component.wrap(f)
+    }
+}
+
 impl Drop for Component {
     fn drop(&mut self) {
         if self.mounted.get() {
@@ -84,7 +189,7 @@ pub fn register_context<T: 'static>(context: T) {
     active(|component| {
         component
             .contexts
-            .insert(context.type_id(), Box::new(context));
+            .insert(context.type_id(), Rc::new(context));
     })
 }
 
@@ -94,8 +199,10 @@ where
     F: FnOnce(&T) -> U,
 {
     STACK.with_borrow(|stack| {
+        // contexts of inner components shadow those of outer ones
         stack
             .iter()
+            .rev()
             .find_map(|component| component.contexts.get(&TypeId::of::<T>()))
             .map(|ctx| {
                 f(ctx.downcast_ref::<T>().unwrap_or_else(|| {
@@ -128,6 +235,21 @@ where
     with_context(Clone::clone)
 }
 
+/// Like [`stardom_reactive::catch_unwind`], but also discards the components whose creation was interrupted.
+#[cfg(panic = "unwind")]
+pub(crate) fn catch_unwind<T, F>(f: F) -> std::thread::Result<T>
+where
+    F: FnOnce() -> T,
+{
+    let depth = STACK.with_borrow(Vec::len);
+    let result = stardom_reactive::catch_unwind(f);
+    if result.is_err() {
+        let interrupted = STACK.with_borrow_mut(|stack| stack.split_off(depth));
+        drop(interrupted);
+    }
+    result
+}
+
 fn active<F>(f: F)
 where
     F: FnOnce(&mut Component),
@@ -141,3 +263,63 @@ where
         })
         .ok();
 }
+
+#[cfg(test)]
+mod tests {
+    use stardom_reactive::{signal, Input, Output};
+
+    use super::*;
+    use crate::{browser::mount, dom::virt::Document, flow::each};
+
+    #[test]
+    fn inner_contexts_shadow_outer_ones() {
+        Node::component(|| {
+            register_context("outer");
+            Node::component(|| {
+                assert_eq!(context::<&str>(), "outer");
+                register_context("inner");
+                assert_eq!(context::<&str>(), "inner");
+                Node::fragment()
+            });
+            assert_eq!(context::<&str>(), "outer");
+            Node::fragment()
+        });
+    }
+
+    #[test]
+    fn rows_run_their_own_hooks() {
+        let document = Document::new();
+        let log = Rc::new(RefCell::new(vec![]));
+        let mut items = None;
+        let _mount = mount(document.body(), || {
+            let items = *items.insert(signal(vec![1]));
+            let log = log.clone();
+            Node::component(move || {
+                each(
+                    move || items.cloned(),
+                    |&n| n,
+                    move |n| {
+                        let (mounted, unmounted) = (log.clone(), log.clone());
+                        on_mount(move || mounted.borrow_mut().push(format!("mount {n}")));
+                        on_unmount(move || unmounted.borrow_mut().push(format!("unmount {n}")));
+                        Node::text(n.to_string())
+                    },
+                )
+            })
+        });
+        let items = items.unwrap();
+        // the first row is created along with its component, the second on a later update
+        assert_eq!(*log.borrow(), ["mount 1"]);
+        items.set(vec![1, 2]);
+        assert_eq!(*log.borrow(), ["mount 1", "mount 2"]);
+
+        items.set(vec![2]);
+        assert_eq!(*log.borrow(), ["mount 1", "mount 2", "unmount 1"]);
+        items.set(vec![]);
+        assert_eq!(
+            *log.borrow(),
+            ["mount 1", "mount 2", "unmount 1", "unmount 2"]
+        );
+        assert_eq!(document.body().inner_html(), "");
+    }
+}
@@ -0,0 +1,639 @@
+//! The native DOM which nodes are mounted into.
+//!
+//! In the browser, this is the DOM exposed by `web_sys`. Mounting or hydrating into a [`virt`] node uses an
+//! in-memory DOM instead, so that client rendering can be exercised outside of the browser.
+
+pub mod virt;
+
+use std::{cell::RefCell, thread_local};
+
+use wasm_bindgen::{intern, prelude::*, JsCast};
+
+use crate::{
+    attrs,
+    events::EventOptions,
+    props::{self, PropValue},
+    util,
+};
+
+thread_local! {
+    static DOCUMENT: RefCell<Option<Document>> = const { RefCell::new(None) };
+}
+
+/// The property of native elements holding their [delegate key](NativeNode::delegate_key).
+const DELEGATE_KEY: &str = "$$stardom";
+
+/// A node of either the browser's DOM or a [virtual](virt) DOM.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum NativeNode {
+    Web(web_sys::Node),
+    Virtual(virt::Node),
+}
+
+impl NativeNode {
+    pub fn as_web(&self) -> Option<&web_sys::Node> {
+        match self {
+            Self::Web(node) => Some(node),
+            Self::Virtual(_) => None,
+        }
+    }
+
+    pub fn as_virtual(&self) -> Option<&virt::Node> {
+        match self {
+            Self::Web(_) => None,
+            Self::Virtual(node) => Some(node),
+        }
+    }
+
+    pub(crate) fn owner_document(&self) -> Document {
+        match self {
+            Self::Web(node) => Document::Web(match node.owner_document() {
+                Some(document) => document,
+                None => node.clone().unchecked_into(),
+            }),
+            Self::Virtual(node) => Document::Virtual(node.owner_document()),
+        }
+    }
+
+    pub(crate) fn parent_node(&self) -> Option<Self> {
+        match self {
+            Self::Web(node) => node.parent_node().map(Self::Web),
+            Self::Virtual(node) => node.parent().map(Self::Virtual),
+        }
+    }
+
+    pub(crate) fn first_child(&self) -> Option<Self> {
+        match self {
+            Self::Web(node) => node.first_child().map(Self::Web),
+            Self::Virtual(node) => node.first_child().map(Self::Virtual),
+        }
+    }
+
+    pub(crate) fn last_child(&self) -> Option<Self> {
+        match self {
+            Self::Web(node) => node.last_child().map(Self::Web),
+            Self::Virtual(node) => node.last_child().map(Self::Virtual),
+        }
+    }
+
+    pub(crate) fn next_sibling(&self) -> Option<Self> {
+        match self {
+            Self::Web(node) => node.next_sibling().map(Self::Web),
+            Self::Virtual(node) => node.next_sibling().map(Self::Virtual),
+        }
+    }
+
+    pub(crate) fn previous_sibling(&self) -> Option<Self> {
+        match self {
+            Self::Web(node) => node.previous_sibling().map(Self::Web),
+            Self::Virtual(node) => node.previous_sibling().map(Self::Virtual),
+        }
+    }
+
+    pub(crate) fn insert_before(&self, child: &Self, before: Option<&Self>) {
+        match self {
+            Self::Web(node) => {
+                node.insert_before(child.web(), before.map(Self::web))
+                    .unwrap();
+            }
+            Self::Virtual(node) => node.insert_before(child.virt(), before.map(Self::virt)),
+        }
+    }
+
+    pub(crate) fn remove_child(&self, child: &Self) {
+        match self {
+            Self::Web(node) => {
+                node.remove_child(child.web()).unwrap();
+            }
+            Self::Virtual(node) => node.remove_child(child.virt()),
+        }
+    }
+
+    /// Returns `true` if `other` is this node or one of its descendants.
+    pub(crate) fn contains(&self, other: &Self) -> bool {
+        match self {
+            Self::Web(node) => node.contains(Some(other.web())),
+            Self::Virtual(node) => node.contains(other.virt()),
+        }
+    }
+
+    /// Returns the local name of an element.
+    pub(crate) fn element_name(&self) -> Option<String> {
+        match self {
+            Self::Web(node) => node
+                .dyn_ref::<web_sys::Element>()
+                .map(web_sys::Element::local_name),
+            Self::Virtual(node) => node.name().map(str::to_string),
+        }
+    }
+
+    pub(crate) fn is_text(&self) -> bool {
+        match self {
+            Self::Web(node) => node.node_type() == web_sys::Node::TEXT_NODE,
+            Self::Virtual(node) => node.is_text(),
+        }
+    }
+
+    /// Returns the data of a comment node.
+    pub(crate) fn comment_data(&self) -> Option<String> {
+        match self {
+            Self::Web(node) => node
+                .dyn_ref::<web_sys::Comment>()
+                .map(|comment| comment.data()),
+            Self::Virtual(node) => node.is_comment().then(|| node.data().unwrap()),
+        }
+    }
+
+    pub(crate) fn text_content(&self) -> String {
+        match self {
+            Self::Web(node) => node.text_content().unwrap_or_default(),
+            Self::Virtual(node) => node.text_content(),
+        }
+    }
+
+    pub(crate) fn set_text_content(&self, value: &str) {
+        match self {
+            Self::Web(node) => node.set_text_content(Some(value)),
+            Self::Virtual(node) => node.set_text_content(value),
+        }
+    }
+
+    pub(crate) fn attribute(&self, key: &str) -> Option<String> {
+        match self {
+            Self::Web(node) => node
+                .dyn_ref::<web_sys::Element>()
+                .and_then(|element| element.get_attribute(key)),
+            Self::Virtual(node) => node.attribute(key),
+        }
+    }
+
+    pub(crate) fn set_attribute(&self, key: &str, value: &str) {
+        match self {
+            Self::Web(node) => {
+                intern(key);
+                node.unchecked_ref::<web_sys::Element>()
+                    .set_attribute(key, value)
+                    .unwrap();
+            }
+            Self::Virtual(node) => node.set_attribute(key, value),
+        }
+    }
+
+    pub(crate) fn remove_attribute(&self, key: &str) {
+        match self {
+            Self::Web(node) => {
+                node.unchecked_ref::<web_sys::Element>()
+                    .remove_attribute(key)
+                    .unwrap();
+            }
+            Self::Virtual(node) => node.remove_attribute(key),
+        }
+    }
+
+    pub(crate) fn toggle_class(&self, name: &str, enabled: bool) {
+        match self {
+            Self::Web(node) => {
+                node.unchecked_ref::<web_sys::Element>()
+                    .class_list()
+                    .toggle_with_force(name, enabled)
+                    .unwrap();
+            }
+            Self::Virtual(node) => {
+                let class = node.attribute("class").unwrap_or_default();
+                node.set_attribute("class", &attrs::toggle_class(&class, name, enabled));
+            }
+        }
+    }
+
+    pub(crate) fn set_style_property(&self, property: &str, value: Option<&str>) {
+        match self {
+            Self::Web(node) => {
+                // every element with inline styles has a `style` property, whether HTML, SVG or MathML
+                let style = js_sys::Reflect::get(node, &JsValue::from_str("style"))
+                    .unwrap()
+                    .unchecked_into::<web_sys::CssStyleDeclaration>();
+                match value {
+                    Some(value) => style.set_property(property, value).unwrap(),
+                    None => {
+                        style.remove_property(property).unwrap();
+                    }
+                }
+            }
+            Self::Virtual(node) => {
+                let style = node.attribute("style").unwrap_or_default();
+                node.set_attribute("style", &attrs::set_style_property(&style, property, value));
+            }
+        }
+    }
+
+    /// Assigns a property, which virtual elements only store if it reflects an attribute, or as the content of a
+    /// textarea for its `value`.
+    pub(crate) fn set_property(&self, key: &str, value: &PropValue) {
+        match self {
+            Self::Web(node) => {
+                js_sys::Reflect::set(node, &JsValue::from_str(key), &value.to_js()).unwrap();
+            }
+            Self::Virtual(node) => match (props::reflected_attr(key), value) {
+                (None, _) | (_, PropValue::Js(_)) => {}
+                (Some("value"), value) if node.name() == Some("textarea") => {
+                    node.set_text_content(&value.to_attr().unwrap_or_default());
+                }
+                (Some(attr), value) => match value.to_attr() {
+                    Some(value) => node.set_attribute(attr, &value),
+                    None => node.remove_attribute(attr),
+                },
+            },
+        }
+    }
+
+    pub(crate) fn add_event_listener(
+        &self,
+        name: &str,
+        listener: &Listener,
+        options: EventOptions,
+    ) {
+        match (self, listener) {
+            (Self::Web(node), Listener::Web(closure)) => {
+                node.add_event_listener_with_callback_and_add_event_listener_options(
+                    intern(name),
+                    closure.as_ref().unchecked_ref(),
+                    &options.to_native(name),
+                )
+                .unwrap();
+            }
+            (Self::Virtual(node), Listener::Virtual(listener)) => {
+                node.add_event_listener(name, listener, options.capture, options.native_once());
+            }
+            _ => mixed(),
+        }
+    }
+
+    pub(crate) fn remove_event_listener(
+        &self,
+        name: &str,
+        listener: &Listener,
+        options: EventOptions,
+    ) {
+        match (self, listener) {
+            (Self::Web(node), Listener::Web(closure)) => {
+                node.remove_event_listener_with_callback_and_bool(
+                    name,
+                    closure.as_ref().unchecked_ref(),
+                    options.capture,
+                )
+                .unwrap();
+            }
+            (Self::Virtual(node), Listener::Virtual(listener)) => {
+                node.remove_event_listener(name, listener, options.capture);
+            }
+            _ => mixed(),
+        }
+    }
+
+    /// Returns the key of the node which handles events delegated to this native node.
+    pub(crate) fn delegate_key(&self) -> Option<u64> {
+        match self {
+            Self::Web(node) => js_sys::Reflect::get(node, &JsValue::from_str(DELEGATE_KEY))
+                .ok()?
+                .as_f64()
+                .map(|key| key as u64),
+            Self::Virtual(node) => node.delegate_key(),
+        }
+    }
+
+    pub(crate) fn set_delegate_key(&self, key: u64) {
+        match self {
+            Self::Web(node) => {
+                js_sys::Reflect::set(
+                    node,
+                    &JsValue::from_str(DELEGATE_KEY),
+                    &JsValue::from_f64(key as f64),
+                )
+                .unwrap();
+            }
+            Self::Virtual(node) => node.set_delegate_key(key),
+        }
+    }
+
+    /// Describes the node for error messages.
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            Self::Web(node) => match node.dyn_ref::<web_sys::Element>() {
+                Some(element) => format!("<{}>", element.local_name()),
+                None => node.node_name(),
+            },
+            Self::Virtual(node) => format!("{node:?}"),
+        }
+    }
+
+    fn web(&self) -> &web_sys::Node {
+        self.as_web().unwrap_or_else(|| mixed())
+    }
+
+    fn virt(&self) -> &virt::Node {
+        self.as_virtual().unwrap_or_else(|| mixed())
+    }
+}
+
+impl From<web_sys::Node> for NativeNode {
+    fn from(node: web_sys::Node) -> Self {
+        Self::Web(node)
+    }
+}
+
+impl From<web_sys::Element> for NativeNode {
+    fn from(element: web_sys::Element) -> Self {
+        Self::Web(element.into())
+    }
+}
+
+impl From<virt::Node> for NativeNode {
+    fn from(node: virt::Node) -> Self {
+        Self::Virtual(node)
+    }
+}
+
+/// The document which native nodes are created by.
+#[derive(Clone)]
+pub(crate) enum Document {
+    Web(web_sys::Document),
+    Virtual(virt::Document),
+}
+
+impl Document {
+    pub(crate) fn create_element(&self, name: &str, namespace: Option<&str>) -> NativeNode {
+        match self {
+            Self::Web(document) => {
+                intern(name);
+                let element = if let Some(ns) = namespace {
+                    intern(ns);
+                    document.create_element_ns(Some(ns), name)
+                } else {
+                    document.create_element(name)
+                };
+                NativeNode::Web(element.unwrap().into())
+            }
+            Self::Virtual(document) => {
+                NativeNode::Virtual(document.create_element_ns(namespace, name))
+            }
+        }
+    }
+
+    pub(crate) fn create_text(&self, data: &str) -> NativeNode {
+        match self {
+            Self::Web(document) => NativeNode::Web(document.create_text_node(data).into()),
+            Self::Virtual(document) => NativeNode::Virtual(document.create_text_node(data)),
+        }
+    }
+
+    /// Parses `html` into a list of detached nodes.
+    pub(crate) fn parse_fragment(&self, html: &str) -> Vec<NativeNode> {
+        match self {
+            Self::Web(_) => {
+                let range = web_sys::Range::new().unwrap();
+                let fragment = range.create_contextual_fragment(html).unwrap();
+                let list = fragment.child_nodes();
+                (0..list.length())
+                    .map(|i| NativeNode::Web(list.item(i).unwrap()))
+                    .collect()
+            }
+            Self::Virtual(document) => document
+                .parse_fragment(html)
+                .into_iter()
+                .map(NativeNode::Virtual)
+                .collect(),
+        }
+    }
+
+    pub(crate) fn head(&self) -> NativeNode {
+        match self {
+            Self::Web(document) => {
+                NativeNode::Web(document.head().expect("document has no head").into())
+            }
+            Self::Virtual(document) => NativeNode::Virtual(document.head()),
+        }
+    }
+
+    pub(crate) fn get_element_by_id(&self, id: &str) -> Option<NativeNode> {
+        match self {
+            Self::Web(document) => document.get_element_by_id(id).map(Into::into),
+            Self::Virtual(document) => document.get_element_by_id(id).map(NativeNode::Virtual),
+        }
+    }
+}
+
+/// Returns the document of the current mount or hydration root, which is the browser's document by default.
+pub(crate) fn document() -> Document {
+    DOCUMENT
+        .with_borrow(Clone::clone)
+        .unwrap_or_else(|| Document::Web(util::document()))
+}
+
+/// Makes the document of `root` the current document, returning the previous one.
+pub(crate) fn replace_document(root: &NativeNode) -> Option<Document> {
+    DOCUMENT.replace(Some(root.owner_document()))
+}
+
+/// Makes `document` the current document again, as returned by [`replace_document`].
+pub(crate) fn restore_document(document: Option<Document>) {
+    DOCUMENT.set(document);
+}
+
+/// Returns `true` if the current document is a [virtual](virt) document.
+pub(crate) fn is_virtual() -> bool {
+    DOCUMENT.with_borrow(|document| matches!(document, Some(Document::Virtual(_))))
+}
+
+/// An event listener of either DOM.
+pub(crate) enum Listener {
+    Web(Closure<dyn FnMut(web_sys::Event)>),
+    Virtual(virt::Listener),
+}
+
+impl Listener {
+    /// Creates a listener for the current document.
+    pub(crate) fn new<F>(mut f: F) -> Self
+    where
+        F: FnMut(NativeEvent) + 'static,
+    {
+        if is_virtual() {
+            Self::Virtual(virt::Listener::new(move |ev: &virt::Event| {
+                f(NativeEvent::Virtual(ev.clone()))
+            }))
+        } else {
+            Self::Web(Closure::new(move |ev| f(NativeEvent::Web(ev))))
+        }
+    }
+}
+
+/// An event of either DOM.
+#[derive(Clone)]
+pub(crate) enum NativeEvent {
+    Web(web_sys::Event),
+    Virtual(virt::Event),
+}
+
+impl NativeEvent {
+    pub(crate) fn target(&self) -> Option<NativeNode> {
+        match self {
+            Self::Web(ev) => ev
+                .target()
+                .and_then(|target| target.dyn_into().ok())
+                .map(NativeNode::Web),
+            Self::Virtual(ev) => ev.target().map(NativeNode::Virtual),
+        }
+    }
+
+    pub(crate) fn key(&self) -> Option<String> {
+        match self {
+            Self::Web(ev) => ev.dyn_ref::<web_sys::KeyboardEvent>().map(|ev| ev.key()),
+            Self::Virtual(ev) => ev.key().map(str::to_string),
+        }
+    }
+
+    pub(crate) fn prevent_default(&self) {
+        match self {
+            Self::Web(ev) => ev.prevent_default(),
+            Self::Virtual(ev) => ev.prevent_default(),
+        }
+    }
+
+    pub(crate) fn default_prevented(&self) -> bool {
+        match self {
+            Self::Web(ev) => ev.default_prevented(),
+            Self::Virtual(ev) => ev.default_prevented(),
+        }
+    }
+
+    pub(crate) fn stop_propagation(&self) {
+        match self {
+            Self::Web(ev) => ev.stop_propagation(),
+            Self::Virtual(ev) => ev.stop_propagation(),
+        }
+    }
+
+    pub(crate) fn propagation_stopped(&self) -> bool {
+        match self {
+            Self::Web(ev) => ev.cancel_bubble(),
+            Self::Virtual(ev) => ev.propagation_stopped(),
+        }
+    }
+}
+
+fn mixed() -> ! {
+    panic!("native nodes of the browser's DOM and a virtual DOM cannot be mixed")
+}
+
+#[cfg(test)]
+mod tests {
+    use stardom_reactive::{effect, signal, Input, Output, Signal};
+
+    use super::{
+        document, is_virtual,
+        virt::{Document, Event},
+    };
+    use crate::{
+        browser::mount,
+        env,
+        events::EventOptions,
+        hydrate::hydrate,
+        render::{render_to_string, Mode},
+        Node,
+    };
+
+    fn counter(count: Signal<i32>) -> Node {
+        let button = Node::element("button".into());
+        button.set_attr("id".into(), "increment".into());
+        button
+            .event(&"click", EventOptions::new(), move |_| {
+                count.update(|n| *n += 1)
+            })
+            .forget();
+
+        let text = Node::text(String::new());
+        button.insert(&text, None);
+        effect({
+            let button = button.clone();
+            move || {
+                text.set_text(count.get().to_string());
+                button.set_attr("data-even".into(), (count.get() % 2 == 0).to_string());
+            }
+        });
+        button
+    }
+
+    #[test]
+    fn mount_dispatches_events() {
+        let document = Document::new();
+        let _mount = mount(document.body(), || counter(signal(0)));
+        assert_eq!(
+            document.body().inner_html(),
+            "<button id=\"increment\" data-even=\"true\">0</button>"
+        );
+
+        let button = document.get_element_by_id("increment").unwrap();
+        button.dispatch_event(&Event::new("click"));
+        assert_eq!(
+            document.body().inner_html(),
+            "<button id=\"increment\" data-even=\"false\">1</button>"
+        );
+    }
+
+    #[test]
+    fn dropped_mounts_restore_the_document() {
+        let outer = Document::new();
+        let outer_mount = mount(outer.body(), Node::fragment);
+        let inner = Document::new();
+        let inner_mount = mount(inner.body(), Node::fragment);
+        assert!(is_virtual() && env::is_browser());
+
+        drop(inner_mount);
+        assert!(matches!(document(), super::Document::Virtual(document) if document == outer));
+        drop(outer_mount);
+        assert!(!is_virtual() && !env::is_browser());
+    }
+
+    #[test]
+    fn hydrate_adopts_server_nodes() {
+        let html = render_to_string(Mode::HYDRATION, || counter(signal(3)));
+        let document = Document::new();
+        document.body().set_inner_html(&html);
+        let button = document.get_element_by_id("increment").unwrap();
+
+        let _mount = hydrate(document.body(), || counter(signal(3)));
+        button.dispatch_event(&Event::new("click"));
+        assert_eq!(
+            document.body().inner_html(),
+            "<button id=\"increment\" data-even=\"true\">4</button>"
+        );
+    }
+
+    #[test]
+    fn virtual_events_reach_handlers() {
+        let document = Document::new();
+        let _mount = mount(document.body(), || {
+            let value = signal(String::new());
+            let input = Node::element("input".into());
+            crate::__macro::bindings::bind_value(&input, value);
+            input
+                .event(&"submit", EventOptions::new(), |ev| {
+                    assert!(ev.as_web().is_none());
+                    ev.prevent_default();
+                })
+                .forget();
+            effect({
+                let input = input.clone();
+                move || {
+                    input.set_attr("data-bound".into(), value.cloned());
+                }
+            });
+            input
+        });
+
+        let input = document.body().first_child().unwrap();
+        input.set_attribute("value", "typed");
+        input.dispatch_event(&Event::new("input"));
+        assert_eq!(input.attribute("data-bound").as_deref(), Some("typed"));
+        assert!(!input.dispatch_event(&Event::new("submit")));
+    }
+}
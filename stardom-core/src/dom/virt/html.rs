@@ -0,0 +1,217 @@
+use super::{Document, Kind, Node};
+use crate::render::{ESCAPABLE_RAW_TEXT, MATHML_NAMESPACE, RAW_TEXT, SVG_NAMESPACE, VOID};
+
+pub(super) fn parse(document: &Document, html: &str) -> Vec<Node> {
+    let root = document.create_element("template");
+    let mut open = vec![root.clone()];
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        let parent = open.last().unwrap().clone();
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let (data, after) = comment.split_once("-->").unwrap_or((comment, ""));
+            parent.append_child(&document.create_comment(data));
+            rest = after;
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            // doctypes and processing instructions are not represented
+            rest = rest.split_once('>').map_or("", |(_, after)| after);
+        } else if let Some(end) = rest.strip_prefix("</") {
+            let (name, after) = end.split_once('>').unwrap_or((end, ""));
+            let name = name.trim();
+            if let Some(index) = open.iter().rposition(|node| node.name() == Some(name)) {
+                open.truncate(index.max(1));
+            }
+            rest = after;
+        } else if rest.starts_with('<') && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic())
+        {
+            let (name, attrs, self_closing, after) = parse_tag(&rest[1..]);
+            rest = after;
+
+            let namespace = match name.as_str() {
+                "svg" => Some(SVG_NAMESPACE),
+                "math" => Some(MATHML_NAMESPACE),
+                _ if parent.name() == Some("foreignObject") => None,
+                _ => parent.namespace(),
+            };
+            let element = document.create_element_ns(namespace, &name);
+            for (key, value) in attrs {
+                element.set_attribute(&key, &value);
+            }
+            parent.append_child(&element);
+
+            let raw = RAW_TEXT.contains(&name.as_str());
+            if namespace.is_none() && (raw || ESCAPABLE_RAW_TEXT.contains(&name.as_str())) {
+                let close = format!("</{name}");
+                let end = rest.find(&close).unwrap_or(rest.len());
+                let text = &rest[..end];
+                if !text.is_empty() {
+                    let text = if raw { text.to_string() } else { decode(text) };
+                    element.append_child(&document.create_text_node(&text));
+                }
+                rest = rest[end..].split_once('>').map_or("", |(_, after)| after);
+            } else if !self_closing && (namespace.is_some() || !VOID.contains(&name.as_str())) {
+                open.push(element);
+            }
+        } else {
+            let end = rest[1..].find('<').map_or(rest.len(), |i| i + 1);
+            parent.append_child(&document.create_text_node(&decode(&rest[..end])));
+            rest = &rest[end..];
+        }
+    }
+
+    let nodes = root.children();
+    for node in &nodes {
+        root.remove_child(node);
+    }
+    nodes
+}
+
+/// Parses a start tag following its `<`, returning its name, attributes, whether it was self-closing, and the
+/// remaining input.
+fn parse_tag(input: &str) -> (String, Vec<(String, String)>, bool, &str) {
+    let is_name_end = |c: char| c.is_ascii_whitespace() || c == '/' || c == '>';
+
+    let end = input.find(is_name_end).unwrap_or(input.len());
+    let name = input[..end].to_string();
+    let mut rest = &input[end..];
+    let mut attrs = vec![];
+
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix("/>") {
+            return (name, attrs, true, after);
+        } else if let Some(after) = rest.strip_prefix('>') {
+            return (name, attrs, false, after);
+        } else if rest.is_empty() {
+            return (name, attrs, false, rest);
+        } else if let Some(after) = rest.strip_prefix('/') {
+            rest = after;
+            continue;
+        }
+
+        let end = rest
+            .find(|c: char| is_name_end(c) || c == '=')
+            .unwrap_or(rest.len());
+        let key = rest[..end].to_string();
+        rest = rest[end..].trim_start();
+
+        let value = if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (value, after) = match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    after[1..].split_once(quote).unwrap_or((&after[1..], ""))
+                }
+                _ => {
+                    let end = after
+                        .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                        .unwrap_or(after.len());
+                    after.split_at(end)
+                }
+            };
+            rest = after;
+            decode(value)
+        } else {
+            String::new()
+        };
+        attrs.push((key, value));
+    }
+}
+
+/// Decodes character references, leaving unrecognized ones as they are.
+fn decode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let decoded = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => '\u{a0}',
+                reference => {
+                    let code = reference.strip_prefix('#')?;
+                    let code = match code.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => code.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end + 1))
+        });
+
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+pub(super) fn serialize(out: &mut String, node: &Node) {
+    match &node.0.kind {
+        Kind::Document => {
+            for child in node.children() {
+                serialize(out, &child);
+            }
+        }
+        Kind::Element { name, attrs, .. } => {
+            out.push('<');
+            out.push_str(name);
+            for (key, value) in &*attrs.borrow() {
+                out.push_str(&format!(" {key}=\"{}\"", escape(value, true)));
+            }
+            out.push('>');
+
+            if node.namespace().is_none() && VOID.contains(&name.as_str()) {
+                return;
+            }
+            for child in node.children() {
+                serialize(out, &child);
+            }
+            out.push_str(&format!("</{name}>"));
+        }
+        Kind::Text(data) => {
+            let raw = node
+                .parent()
+                .is_some_and(|parent| parent.name().is_some_and(|name| RAW_TEXT.contains(&name)));
+            if raw {
+                out.push_str(&data.borrow());
+            } else {
+                out.push_str(&escape(&data.borrow(), false));
+            }
+        }
+        Kind::Comment(data) => {
+            out.push_str(&format!("<!--{}-->", data.borrow()));
+        }
+    }
+}
+
+/// Escapes text as the browser does when serializing, which differs from how the renderer escapes it.
+fn escape(s: &str, attr: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '\u{a0}' => out.push_str("&nbsp;"),
+            '"' if attr => out.push_str("&quot;"),
+            '<' if !attr => out.push_str("&lt;"),
+            '>' if !attr => out.push_str("&gt;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
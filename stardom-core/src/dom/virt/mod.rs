@@ -0,0 +1,604 @@
+//! An in-memory DOM, used in place of the browser's DOM outside of the browser.
+//!
+//! Mounting or hydrating into a virtual node runs the client-side code paths natively, which allows them to be
+//! tested without a browser:
+//!
+//! ```ignore
+//! let document = Document::new();
+//! let _mount = mount(document.body(), app);
+//!
+//! let button = document.get_element_by_id("increment").unwrap();
+//! button.dispatch_event(&Event::new("click").bubbles(true));
+//! assert_eq!(document.body().inner_html(), "...");
+//! ```
+//!
+//! Only the parts of the DOM used by stardom are implemented. HTML is parsed leniently, without the error
+//! recovery of a browser.
+
+mod html;
+
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+    rc::{Rc, Weak},
+};
+
+use indexmap::IndexMap;
+
+/// A node within a virtual [`Document`].
+#[derive(Clone)]
+pub struct Node(Rc<RawNode>);
+
+struct RawNode {
+    kind: Kind,
+    document: Weak<Self>,
+    parent: RefCell<Weak<Self>>,
+    children: RefCell<Vec<Node>>,
+    listeners: RefCell<Vec<Registered>>,
+    delegate_key: Cell<Option<u64>>,
+}
+
+enum Kind {
+    Document,
+    Element {
+        name: String,
+        namespace: Option<String>,
+        attrs: RefCell<IndexMap<String, String>>,
+    },
+    Text(RefCell<String>),
+    Comment(RefCell<String>),
+}
+
+struct Registered {
+    name: String,
+    listener: Listener,
+    capture: bool,
+    once: bool,
+}
+
+impl Node {
+    fn create(document: &Document, kind: Kind) -> Self {
+        Self(Rc::new(RawNode {
+            kind,
+            document: Rc::downgrade(&document.0 .0),
+            parent: RefCell::default(),
+            children: RefCell::default(),
+            listeners: RefCell::default(),
+            delegate_key: Cell::default(),
+        }))
+    }
+
+    /// Returns the document this node was created by.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the document has been dropped.
+    pub fn owner_document(&self) -> Document {
+        match self.0.kind {
+            Kind::Document => Document(self.clone()),
+            _ => Document(Self(
+                self.0.document.upgrade().expect("virtual document dropped"),
+            )),
+        }
+    }
+
+    pub fn is_element(&self) -> bool {
+        matches!(self.0.kind, Kind::Element { .. })
+    }
+
+    pub fn is_text(&self) -> bool {
+        matches!(self.0.kind, Kind::Text(_))
+    }
+
+    pub fn is_comment(&self) -> bool {
+        matches!(self.0.kind, Kind::Comment(_))
+    }
+
+    /// Returns the tag name of an element.
+    pub fn name(&self) -> Option<&str> {
+        match &self.0.kind {
+            Kind::Element { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+
+    pub fn namespace(&self) -> Option<&str> {
+        match &self.0.kind {
+            Kind::Element { namespace, .. } => namespace.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the data of a text or comment node.
+    pub fn data(&self) -> Option<String> {
+        match &self.0.kind {
+            Kind::Text(data) | Kind::Comment(data) => Some(data.borrow().clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns the concatenated data of all text nodes beneath this node, including itself.
+    pub fn text_content(&self) -> String {
+        match &self.0.kind {
+            Kind::Text(data) => data.borrow().clone(),
+            Kind::Comment(_) => String::new(),
+            Kind::Document | Kind::Element { .. } => {
+                self.children().iter().map(Self::text_content).collect()
+            }
+        }
+    }
+
+    /// Sets the data of a text or comment node, or replaces the children of any other node with a single text
+    /// node.
+    pub fn set_text_content(&self, value: &str) {
+        match &self.0.kind {
+            Kind::Text(data) | Kind::Comment(data) => *data.borrow_mut() = value.to_string(),
+            Kind::Document | Kind::Element { .. } => {
+                for child in self.children() {
+                    self.remove_child(&child);
+                }
+                if !value.is_empty() {
+                    self.append_child(&self.owner_document().create_text_node(value));
+                }
+            }
+        }
+    }
+
+    pub fn attribute(&self, key: &str) -> Option<String> {
+        match &self.0.kind {
+            Kind::Element { attrs, .. } => attrs.borrow().get(key).cloned(),
+            _ => None,
+        }
+    }
+
+    pub fn attributes(&self) -> Vec<(String, String)> {
+        match &self.0.kind {
+            Kind::Element { attrs, .. } => attrs
+                .borrow()
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// # Panics
+    ///
+    /// Panics if this node is not an element.
+    pub fn set_attribute(&self, key: &str, value: &str) {
+        match &self.0.kind {
+            Kind::Element { attrs, .. } => {
+                attrs
+                    .borrow_mut()
+                    .insert(key.to_string(), value.to_string());
+            }
+            _ => panic!("attributes can only be set on elements"),
+        }
+    }
+
+    pub fn remove_attribute(&self, key: &str) {
+        if let Kind::Element { attrs, .. } = &self.0.kind {
+            attrs.borrow_mut().shift_remove(key);
+        }
+    }
+
+    // Tree //
+
+    pub fn parent(&self) -> Option<Self> {
+        self.0.parent.borrow().upgrade().map(Self)
+    }
+
+    pub fn children(&self) -> Vec<Self> {
+        self.0.children.borrow().clone()
+    }
+
+    pub fn first_child(&self) -> Option<Self> {
+        self.0.children.borrow().first().cloned()
+    }
+
+    pub fn last_child(&self) -> Option<Self> {
+        self.0.children.borrow().last().cloned()
+    }
+
+    pub fn next_sibling(&self) -> Option<Self> {
+        self.sibling(1)
+    }
+
+    pub fn previous_sibling(&self) -> Option<Self> {
+        self.sibling(-1)
+    }
+
+    fn sibling(&self, offset: isize) -> Option<Self> {
+        let parent = self.parent()?;
+        let children = parent.0.children.borrow();
+        let index = children.iter().position(|child| child == self)?;
+        children.get(index.checked_add_signed(offset)?).cloned()
+    }
+
+    pub fn append_child(&self, child: &Self) {
+        self.insert_before(child, None);
+    }
+
+    /// Inserts `child` before `before`, or at the end if `before` is `None`, removing it from its current parent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `before` is not a child of this node, or if `child` is an ancestor of this node.
+    pub fn insert_before(&self, child: &Self, before: Option<&Self>) {
+        assert!(
+            !child.contains(self),
+            "cannot insert a node into its own subtree"
+        );
+        if let Some(parent) = child.parent() {
+            parent.remove_child(child);
+        }
+
+        let mut children = self.0.children.borrow_mut();
+        let index = match before {
+            Some(before) => children
+                .iter()
+                .position(|node| node == before)
+                .expect("reference node not a child of self"),
+            None => children.len(),
+        };
+        children.insert(index, child.clone());
+        child.0.parent.replace(Rc::downgrade(&self.0));
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `child` is not a child of this node.
+    pub fn remove_child(&self, child: &Self) {
+        let mut children = self.0.children.borrow_mut();
+        let index = children
+            .iter()
+            .position(|node| node == child)
+            .expect("removal node not a child of self");
+        children.remove(index);
+        child.0.parent.replace(Weak::new());
+    }
+
+    /// Returns `true` if `other` is this node or one of its descendants.
+    pub fn contains(&self, other: &Self) -> bool {
+        let mut node = Some(other.clone());
+        while let Some(current) = node {
+            if current == *self {
+                return true;
+            }
+            node = current.parent();
+        }
+        false
+    }
+
+    /// Finds the first element with the given id beneath this node, including itself.
+    pub fn find_by_id(&self, id: &str) -> Option<Self> {
+        if self.attribute("id").as_deref() == Some(id) {
+            return Some(self.clone());
+        }
+        self.children()
+            .iter()
+            .find_map(|child| child.find_by_id(id))
+    }
+
+    // HTML //
+
+    /// Serializes the children of this node.
+    pub fn inner_html(&self) -> String {
+        let mut out = String::new();
+        for child in self.children() {
+            html::serialize(&mut out, &child);
+        }
+        out
+    }
+
+    /// Serializes this node, including its children.
+    pub fn outer_html(&self) -> String {
+        let mut out = String::new();
+        html::serialize(&mut out, self);
+        out
+    }
+
+    /// Replaces the children of this node with the nodes parsed from `html`.
+    pub fn set_inner_html(&self, html: &str) {
+        for child in self.children() {
+            self.remove_child(&child);
+        }
+        for child in self.owner_document().parse_fragment(html) {
+            self.append_child(&child);
+        }
+    }
+
+    // Events //
+
+    pub fn add_event_listener(&self, name: &str, listener: &Listener, capture: bool, once: bool) {
+        let mut listeners = self.0.listeners.borrow_mut();
+        let registered = listeners.iter().any(|registered| {
+            registered.name == name
+                && registered.listener == *listener
+                && registered.capture == capture
+        });
+        if !registered {
+            listeners.push(Registered {
+                name: name.to_string(),
+                listener: listener.clone(),
+                capture,
+                once,
+            });
+        }
+    }
+
+    pub fn remove_event_listener(&self, name: &str, listener: &Listener, capture: bool) {
+        self.0.listeners.borrow_mut().retain(|registered| {
+            registered.name != name
+                || registered.listener != *listener
+                || registered.capture != capture
+        });
+    }
+
+    /// Dispatches `event` with this node as its target, returning `false` if the default action was prevented.
+    ///
+    /// Listeners are invoked in the same order as in the browser: capturing listeners from the root downwards,
+    /// then the listeners of the target, then bubbling listeners from the target upwards if the event bubbles.
+    pub fn dispatch_event(&self, event: &Event) -> bool {
+        event.0.target.replace(Some(self.clone()));
+        event.0.stopped.set(false);
+
+        let mut path = vec![];
+        let mut node = self.parent();
+        while let Some(current) = node {
+            node = current.parent();
+            path.push(current);
+        }
+
+        for node in path.iter().rev() {
+            node.invoke(event, Some(true));
+        }
+        self.invoke(event, None);
+        if event.0.bubbles.get() {
+            for node in &path {
+                node.invoke(event, Some(false));
+            }
+        }
+
+        event.0.current_target.replace(None);
+        !event.default_prevented()
+    }
+
+    /// Invokes the listeners for `event`, optionally only those registered for the given phase.
+    fn invoke(&self, event: &Event, capture: Option<bool>) {
+        if event.0.stopped.get() {
+            return;
+        }
+
+        let matching = {
+            let mut listeners = self.0.listeners.borrow_mut();
+            let matches = |registered: &Registered| {
+                registered.name == event.name()
+                    && capture.is_none_or(|capture| registered.capture == capture)
+            };
+            let matching = listeners
+                .iter()
+                .filter(|registered| matches(registered))
+                .map(|registered| registered.listener.clone())
+                .collect::<Vec<_>>();
+            listeners.retain(|registered| !(registered.once && matches(registered)));
+            matching
+        };
+
+        event.0.current_target.replace(Some(self.clone()));
+        for listener in matching {
+            (listener.0.borrow_mut())(event);
+        }
+    }
+
+    /// Returns the key identifying the node which handles events delegated to this node.
+    pub(crate) fn delegate_key(&self) -> Option<u64> {
+        self.0.delegate_key.get()
+    }
+
+    pub(crate) fn set_delegate_key(&self, key: u64) {
+        self.0.delegate_key.set(Some(key));
+    }
+
+    fn describe(&self) -> String {
+        match &self.0.kind {
+            Kind::Document => "#document".to_string(),
+            Kind::Element { name, .. } => format!("<{name}>"),
+            Kind::Text(data) => format!("{:?}", data.borrow()),
+            Kind::Comment(data) => format!("<!--{}-->", data.borrow()),
+        }
+    }
+}
+
+impl Eq for Node {}
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.describe())
+    }
+}
+
+/// A virtual document, containing an empty `head` and `body`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Document(Node);
+
+impl Document {
+    pub fn new() -> Self {
+        let document = Self(Node(Rc::new_cyclic(|weak| RawNode {
+            kind: Kind::Document,
+            document: weak.clone(),
+            parent: RefCell::default(),
+            children: RefCell::default(),
+            listeners: RefCell::default(),
+            delegate_key: Cell::default(),
+        })));
+
+        let html = document.create_element("html");
+        html.append_child(&document.create_element("head"));
+        html.append_child(&document.create_element("body"));
+        document.0.append_child(&html);
+        document
+    }
+
+    /// Returns the root node of the document.
+    pub fn node(&self) -> &Node {
+        &self.0
+    }
+
+    pub fn head(&self) -> Node {
+        self.child_of_html("head")
+    }
+
+    pub fn body(&self) -> Node {
+        self.child_of_html("body")
+    }
+
+    fn child_of_html(&self, name: &str) -> Node {
+        self.0
+            .children()
+            .iter()
+            .flat_map(Node::children)
+            .find(|node| node.name() == Some(name))
+            .unwrap_or_else(|| panic!("virtual document has no `{name}`"))
+    }
+
+    pub fn create_element(&self, name: &str) -> Node {
+        self.create_element_ns(None, name)
+    }
+
+    pub fn create_element_ns(&self, namespace: Option<&str>, name: &str) -> Node {
+        Node::create(
+            self,
+            Kind::Element {
+                name: name.to_string(),
+                namespace: namespace.map(str::to_string),
+                attrs: RefCell::default(),
+            },
+        )
+    }
+
+    pub fn create_text_node(&self, data: &str) -> Node {
+        Node::create(self, Kind::Text(RefCell::new(data.to_string())))
+    }
+
+    pub fn create_comment(&self, data: &str) -> Node {
+        Node::create(self, Kind::Comment(RefCell::new(data.to_string())))
+    }
+
+    /// Parses `html` into a list of detached nodes.
+    pub fn parse_fragment(&self, html: &str) -> Vec<Node> {
+        html::parse(self, html)
+    }
+
+    pub fn get_element_by_id(&self, id: &str) -> Option<Node> {
+        self.0.find_by_id(id)
+    }
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An event dispatched through [`Node::dispatch_event`].
+#[derive(Clone)]
+pub struct Event(Rc<RawEvent>);
+
+struct RawEvent {
+    name: String,
+    key: Option<String>,
+    bubbles: Cell<bool>,
+    default_prevented: Cell<bool>,
+    stopped: Cell<bool>,
+    target: RefCell<Option<Node>>,
+    current_target: RefCell<Option<Node>>,
+}
+
+impl Event {
+    /// Creates an event which does not bubble, like `new Event(name)`.
+    pub fn new(name: &str) -> Self {
+        Self(Rc::new(RawEvent {
+            name: name.to_string(),
+            key: None,
+            bubbles: Cell::new(false),
+            default_prevented: Cell::new(false),
+            stopped: Cell::new(false),
+            target: RefCell::default(),
+            current_target: RefCell::default(),
+        }))
+    }
+
+    pub fn bubbles(self, value: bool) -> Self {
+        self.0.bubbles.set(value);
+        self
+    }
+
+    /// Creates a keyboard event for `key`, which bubbles like `new KeyboardEvent(name, { key, bubbles: true })`.
+    pub fn keyboard(name: &str, key: &str) -> Self {
+        Self(Rc::new(RawEvent {
+            key: Some(key.to_string()),
+            ..Rc::into_inner(Self::new(name).0).unwrap()
+        }))
+        .bubbles(true)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    /// Returns the key of a keyboard event.
+    pub fn key(&self) -> Option<&str> {
+        self.0.key.as_deref()
+    }
+
+    pub fn target(&self) -> Option<Node> {
+        self.0.target.borrow().clone()
+    }
+
+    pub fn current_target(&self) -> Option<Node> {
+        self.0.current_target.borrow().clone()
+    }
+
+    pub fn prevent_default(&self) {
+        self.0.default_prevented.set(true);
+    }
+
+    pub fn default_prevented(&self) -> bool {
+        self.0.default_prevented.get()
+    }
+
+    pub fn stop_propagation(&self) {
+        self.0.stopped.set(true);
+    }
+
+    pub fn propagation_stopped(&self) -> bool {
+        self.0.stopped.get()
+    }
+}
+
+/// An event listener, which is compared by identity when removed.
+#[derive(Clone)]
+pub struct Listener(Rc<RefCell<Handler>>);
+
+type Handler = dyn FnMut(&Event);
+
+impl Listener {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: FnMut(&Event) + 'static,
+    {
+        Self(Rc::new(RefCell::new(f)))
+    }
+}
+
+impl PartialEq for Listener {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
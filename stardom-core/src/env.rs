@@ -1,5 +1,7 @@
 use std::{cell::Cell, thread_local};
 
+use crate::dom;
+
 thread_local! {
     static ENV: Cell<Env> = const { Cell::new(Env::Render) };
 }
@@ -20,7 +22,7 @@ pub(crate) fn is_hydrating() -> bool {
 }
 
 pub(crate) fn replace(env: Env) -> Env {
-    let browser = cfg!(target_family = "wasm") && web_sys::window().is_some();
+    let browser = dom::is_virtual() || cfg!(target_family = "wasm") && web_sys::window().is_some();
 
     if !browser && matches!(env, Env::Browser | Env::Hydrate) {
         panic!("client rendering unsupported in this environment");
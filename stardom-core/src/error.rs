@@ -0,0 +1,339 @@
+use std::{any::Any, error, fmt, rc::Rc};
+
+use stardom_reactive::{effect, signal, Input, Output, Signal};
+
+use crate::{
+    component::{register_context, try_context},
+    flow::switch,
+    node::{IntoNode, Node},
+};
+
+/// An error caught by an [`error_boundary`].
+#[derive(Clone)]
+pub struct Error(Rc<dyn error::Error>);
+
+impl Error {
+    pub fn new<E>(error: E) -> Self
+    where
+        E: error::Error + 'static,
+    {
+        Self(Rc::new(error))
+    }
+
+    pub fn downcast_ref<E>(&self) -> Option<&E>
+    where
+        E: error::Error + 'static,
+    {
+        self.0.downcast_ref()
+    }
+
+    fn from_panic(payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast::<&str>() {
+                Ok(message) => message.to_string(),
+                Err(_) => "component panicked".to_string(),
+            },
+        };
+        Self::new(Panic(message))
+    }
+}
+
+impl<E> From<E> for Error
+where
+    E: error::Error + 'static,
+{
+    fn from(error: E) -> Self {
+        Self::new(error)
+    }
+}
+
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// The error of a panic caught by an [`error_boundary`].
+#[derive(Debug)]
+pub struct Panic(pub String);
+
+impl fmt::Display for Panic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl error::Error for Panic {}
+
+/// Clears the error of an [`error_boundary`], rendering its children again.
+#[derive(Clone, Copy)]
+pub struct Reset(Signal<Option<Error>>);
+
+impl Reset {
+    pub fn reset(&self) {
+        self.0.set(None);
+    }
+}
+
+/// The error boundary that errors raised beneath it are reported to.
+#[derive(Clone, Copy)]
+struct Handler(Signal<Option<Error>>);
+
+impl Handler {
+    fn report(&self, error: Error) {
+        if self.0.with(Option::is_none) {
+            self.0.set(Some(error));
+        }
+    }
+}
+
+/// Renders `children`, or `fallback` with the error once an error is raised beneath it.
+///
+/// Errors are caught when returned from [`try_component`] or [`try_effect`], and from a `Result` rendered as a
+/// node, including within the effects of reactive node statements. Panics are caught when children panic during
+/// creation or within a [`try_effect`].
+///
+/// # Panics
+///
+/// Panics can only be caught when unwinding is enabled (`panic = "unwind"`). This is not the case for `wasm32`
+/// targets, which abort on panic by default, so a panic within the boundary aborts the whole app there rather
+/// than rendering `fallback`. Return errors instead of panicking for them to be caught in the browser.
+///
+/// ```no_run
+/// # use stardom_core::{error::error_boundary, named::elements::*, Node};
+/// # use stardom_macros::fragment;
+/// # fn profile() -> Node {
+/// #     Node::fragment()
+/// # }
+/// error_boundary(
+///     |error, reset| fragment! {
+///         p!(error.to_string());
+///         button! {
+///             on:click => move |_| reset.reset();
+///             "Retry";
+///         }
+///     },
+///     || profile(),
+/// )
+/// # ;
+/// ```
+pub fn error_boundary<E, F>(fallback: E, children: F) -> Node
+where
+    E: Fn(Error, Reset) -> Node + 'static,
+    F: Fn() -> Node + 'static,
+{
+    let error = signal(None::<Error>);
+    let handler = Handler(error);
+
+    switch(
+        move || error.cloned(),
+        move |current| match current {
+            Some(current) => fallback(current.clone(), Reset(error)),
+            None => Node::component(|| {
+                register_context(handler);
+                catch(&children).unwrap_or_else(|error| {
+                    handler.report(error);
+                    Node::fragment()
+                })
+            }),
+        },
+    )
+}
+
+/// Creates a component from a fallible function, reporting errors to the nearest [`error_boundary`].
+///
+/// This is what `#[component]` expands to for functions returning `Result`, `std::result::Result` or
+/// `core::result::Result`. Functions returning an alias such as `io::Result` are marked with
+/// `#[component(fallible)]` instead.
+///
+/// # Panics
+///
+/// Panics with the error if there is no enclosing error boundary. A panic within `f` is only caught by the
+/// boundary when unwinding is enabled, which is not the case for `wasm32` targets by default (see
+/// [`error_boundary`]).
+pub fn try_component<E, F>(f: F) -> Node
+where
+    E: Into<Error>,
+    F: FnOnce() -> Result<Node, E>,
+{
+    Node::component(|| {
+        f().unwrap_or_else(|error| {
+            report(try_context(), error.into());
+            Node::fragment()
+        })
+    })
+}
+
+/// Creates an effect from a fallible function, reporting errors and panics to the [`error_boundary`] it was
+/// created beneath.
+///
+/// # Panics
+///
+/// Panics with the error if there is no enclosing error boundary. Like in [`try_component`], panics are only
+/// caught when unwinding is enabled.
+pub fn try_effect<E, F>(mut f: F)
+where
+    E: Into<Error>,
+    F: FnMut() -> Result<(), E> + 'static,
+{
+    let handler = try_context::<Handler>();
+    effect(move || {
+        if let Err(error) = catch(|| f().map_err(Into::into)).and_then(|result| result) {
+            report(handler, error);
+        }
+    });
+}
+
+/// Renders the node, or nothing while reporting the error to the enclosing [`error_boundary`].
+///
+/// When replacing a node within an effect, such as that of a reactive node statement, the boundary is found
+/// through the node tree, as the components have already been created.
+impl<N, E> IntoNode for Result<N, E>
+where
+    N: IntoNode,
+    E: Into<Error>,
+{
+    fn into_node(self) -> Node {
+        self.map(IntoNode::into_node).unwrap_or_else(|error| {
+            report(try_context(), error.into());
+            Node::fragment()
+        })
+    }
+
+    fn replace_self(self, parent: &Node, target: &Node) -> Node {
+        match self {
+            Ok(node) => node.replace_self(parent, target),
+            Err(error) => {
+                report(parent.tree_context().or_else(try_context), error.into());
+                Node::fragment().replace_self(parent, target)
+            }
+        }
+    }
+}
+
+fn report(handler: Option<Handler>, error: Error) {
+    match handler {
+        Some(handler) => handler.report(error),
+        None => panic!("uncaught component error: {error}"),
+    }
+}
+
+fn catch<T, F>(f: F) -> Result<T, Error>
+where
+    F: FnOnce() -> T,
+{
+    #[cfg(panic = "unwind")]
+    return crate::component::catch_unwind(f).map_err(Error::from_panic);
+
+    #[cfg(not(panic = "unwind"))]
+    Ok(f())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, io};
+
+    use stardom_reactive::run;
+
+    use super::*;
+    use crate::node::NodeKind;
+
+    fn text(node: &Node) -> String {
+        match node.kind() {
+            NodeKind::Text(content) => content.borrow().clone(),
+            _ => text(&node.children()[0]),
+        }
+    }
+
+    #[test]
+    fn boundary_catches_and_resets() {
+        run(|dispose| {
+            let reset = Rc::new(Cell::new(None));
+            let fail = signal(true);
+            let node = error_boundary(
+                {
+                    let reset = reset.clone();
+                    move |error, r| {
+                        reset.set(Some(r));
+                        Node::text(error.to_string())
+                    }
+                },
+                move || {
+                    try_component(|| match fail.get() {
+                        true => Err(io::Error::other("failed")),
+                        false => Ok(Node::text("ok".into())),
+                    })
+                },
+            );
+            assert_eq!(text(&node), "failed");
+
+            fail.set(false);
+            reset.take().unwrap().reset();
+            assert_eq!(text(&node), "ok");
+
+            dispose();
+        });
+    }
+
+    #[test]
+    fn boundary_catches_errors_of_reactive_nodes() {
+        run(|dispose| {
+            let fail = signal(false);
+            let node = error_boundary(
+                |error, _| Node::text(error.to_string()),
+                move || {
+                    // as expanded from a reactive node statement
+                    let parent = Node::fragment();
+                    let mut current = Node::fragment();
+                    parent.insert(&current, None);
+                    effect({
+                        let parent = parent.clone();
+                        move || {
+                            let result = match fail.get() {
+                                true => Err(io::Error::other("failed later")),
+                                false => Ok("ok"),
+                            };
+                            current = result.replace_self(&parent, &current);
+                        }
+                    });
+                    parent
+                },
+            );
+            assert_eq!(text(&node), "ok");
+
+            fail.set(true);
+            assert_eq!(text(&node), "failed later");
+
+            dispose();
+        });
+    }
+
+    #[test]
+    fn boundary_catches_effect_panics() {
+        run(|dispose| {
+            let node = error_boundary(
+                |error, _| Node::text(error.to_string()),
+                || {
+                    try_effect(|| -> Result<(), io::Error> { panic!("effect panicked") });
+                    Node::fragment()
+                },
+            );
+            assert_eq!(text(&node), "effect panicked");
+
+            dispose();
+        });
+    }
+}
@@ -1,10 +1,20 @@
+use std::marker::PhantomData;
+
 use wasm_bindgen::JsCast;
 
+use crate::dom::{virt, NativeEvent, NativeNode};
+
 #[derive(Clone, Copy, Default, Debug)]
 pub struct EventOptions {
     pub capture: bool,
     pub once: bool,
     pub passive: Option<bool>,
+    pub prevent_default: bool,
+    pub stop_propagation: bool,
+    /// Only handles events dispatched to the element itself, rather than to one of its descendants.
+    pub self_only: bool,
+    /// Only handles keyboard events for the given `key`, such as `"Enter"`.
+    pub key: Option<&'static str>,
 }
 
 impl EventOptions {
@@ -27,25 +37,132 @@ impl EventOptions {
         self
     }
 
-    pub fn to_native(self, event: &str) -> web_sys::AddEventListenerOptions {
-        let Self {
-            capture,
-            once,
-            passive,
-        } = self;
+    pub fn prevent_default(mut self, value: bool) -> Self {
+        self.prevent_default = value;
+        self
+    }
+
+    pub fn stop_propagation(mut self, value: bool) -> Self {
+        self.stop_propagation = value;
+        self
+    }
+
+    pub fn self_only(mut self, value: bool) -> Self {
+        self.self_only = value;
+        self
+    }
+
+    pub fn key(mut self, value: &'static str) -> Self {
+        self.key = Some(value);
+        self
+    }
 
-        let passive = match passive {
+    /// Returns whether the listener can be removed by the DOM after its first event, which is not the case if
+    /// events may be filtered out.
+    pub(crate) fn native_once(&self) -> bool {
+        self.once && !self.self_only && self.key.is_none()
+    }
+
+    /// Returns whether the listener is passive, which it is by default for `scroll` and `wheel` events unless
+    /// the default action is prevented.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the listener is explicitly passive while preventing the default action, which a passive
+    /// listener cannot do.
+    pub(crate) fn native_passive(&self, event: &str) -> bool {
+        match self.passive {
+            Some(true) if self.prevent_default => {
+                panic!("`{event}` listener cannot be passive and prevent the default action")
+            }
             Some(value) => value,
             // TODO: check if any others should be passive by default
-            None => matches!(event, "scroll" | "wheel"),
-        };
+            None => !self.prevent_default && matches!(event, "scroll" | "wheel"),
+        }
+    }
 
-        let mut opts = web_sys::AddEventListenerOptions::new();
-        opts.capture(capture).once(once).passive(passive);
+    /// Converts the options into those of a browser listener for `event`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the listener is explicitly [passive](Self::passive) while [preventing the
+    /// default](Self::prevent_default) action.
+    pub fn to_native(self, event: &str) -> web_sys::AddEventListenerOptions {
+        let once = self.native_once();
+        let passive = self.native_passive(event);
+        let Self { capture, .. } = self;
+
+        let opts = web_sys::AddEventListenerOptions::new();
+        opts.set_capture(capture);
+        opts.set_once(once);
+        opts.set_passive(passive);
         opts
     }
 }
 
+/// An event passed to a handler, dispatched by either the browser's DOM or a [virtual](virt) DOM.
+///
+/// Operations common to both are available directly, while the browser's event of type `T` is reached through
+/// [`as_web`](Self::as_web).
+pub struct Event<T> {
+    native: NativeEvent,
+    current_target: Option<NativeNode>,
+    _marker: PhantomData<T>,
+}
+
+impl<T: JsCast> Event<T> {
+    pub(crate) fn new(native: NativeEvent, current_target: Option<NativeNode>) -> Self {
+        if let NativeEvent::Web(ev) = &native {
+            assert!(ev.dyn_ref::<T>().is_some(), "event type mismatch");
+        }
+        Self {
+            native,
+            current_target,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn as_web(&self) -> Option<&T> {
+        match &self.native {
+            NativeEvent::Web(ev) => Some(ev.unchecked_ref()),
+            NativeEvent::Virtual(_) => None,
+        }
+    }
+
+    pub fn as_virtual(&self) -> Option<&virt::Event> {
+        match &self.native {
+            NativeEvent::Web(_) => None,
+            NativeEvent::Virtual(ev) => Some(ev),
+        }
+    }
+
+    pub fn target(&self) -> Option<NativeNode> {
+        self.native.target()
+    }
+
+    /// Returns the element the handler was added to, even if the event was [delegated](crate::browser::MountOptions).
+    pub fn current_target(&self) -> Option<NativeNode> {
+        self.current_target.clone()
+    }
+
+    /// Returns the key of a keyboard event.
+    pub fn key(&self) -> Option<String> {
+        self.native.key()
+    }
+
+    pub fn prevent_default(&self) {
+        self.native.prevent_default();
+    }
+
+    pub fn default_prevented(&self) -> bool {
+        self.native.default_prevented()
+    }
+
+    pub fn stop_propagation(&self) {
+        self.native.stop_propagation();
+    }
+}
+
 pub trait EventKey {
     type Event: JsCast;
 
@@ -67,3 +184,71 @@ impl EventKey for String {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::{
+        browser::mount,
+        dom::virt::{Document, Event},
+        Node,
+    };
+
+    #[test]
+    fn preventing_default_is_not_passive() {
+        let options = EventOptions::new();
+        assert!(options.native_passive("wheel"));
+        assert!(!options.native_passive("click"));
+        assert!(!options.prevent_default(true).native_passive("wheel"));
+        assert!(!options
+            .prevent_default(true)
+            .passive(false)
+            .native_passive("scroll"));
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot be passive")]
+    fn passive_cannot_prevent_default() {
+        EventOptions::new()
+            .passive(true)
+            .prevent_default(true)
+            .native_passive("wheel");
+    }
+
+    #[test]
+    fn event_modifiers_filter_events() {
+        let document = Document::new();
+        let clicks = Rc::new(Cell::new(0));
+        let enters = Rc::new(Cell::new(0));
+        let _mount = mount(document.body(), || {
+            let button = Node::element("button".into());
+            button.insert(&Node::element("span".into()), None);
+
+            let clicks = clicks.clone();
+            let options = EventOptions::new().self_only(true).prevent_default(true);
+            button
+                .event(&"click", options, move |_| clicks.set(clicks.get() + 1))
+                .forget();
+
+            let enters = enters.clone();
+            let options = EventOptions::new().key("Enter").once(true);
+            button
+                .event(&"keydown", options, move |_| enters.set(enters.get() + 1))
+                .forget();
+            button
+        });
+
+        let button = document.body().first_child().unwrap();
+        let span = button.first_child().unwrap();
+        assert!(span.dispatch_event(&Event::new("click").bubbles(true)));
+        assert!(!button.dispatch_event(&Event::new("click")));
+        assert_eq!(clicks.get(), 1);
+
+        button.dispatch_event(&Event::keyboard("keydown", "a"));
+        button.dispatch_event(&Event::keyboard("keydown", "Enter"));
+        button.dispatch_event(&Event::keyboard("keydown", "Enter"));
+        assert_eq!(enters.get(), 1);
+    }
+}
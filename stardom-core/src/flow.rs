@@ -0,0 +1,383 @@
+use std::{cell::Cell, collections::HashMap, hash::Hash};
+
+use stardom_reactive::{effect, untrack, Scope};
+
+use crate::{
+    component::Contexts,
+    dom::{self, virt, NativeNode},
+    env::{is_browser, is_hydrating},
+    node::{Deferred, Node, NodeKind},
+};
+
+struct Row<K> {
+    key: K,
+    node: Node,
+    scope: Scope,
+}
+
+/// Renders a keyed list.
+///
+/// Whenever `items` changes, the new keys are diffed against the old ones: rows with new keys are rendered,
+/// rows whose keys disappeared are removed and disposed, and the remaining rows are moved into place without
+/// being rendered again. Each row is rendered within its own scope, which lives for as long as its key does, and
+/// the [`on_mount`](crate::component::on_mount) and [`on_unmount`](crate::component::on_unmount) hooks of a
+/// row run when it is added and removed.
+pub fn each<T, I, K, F, KF, RF>(mut items: F, key_fn: KF, render_fn: RF) -> Node
+where
+    I: IntoIterator<Item = T>,
+    K: Eq + Hash + 'static,
+    F: FnMut() -> I + 'static,
+    KF: Fn(&T) -> K + 'static,
+    RF: Fn(T) -> Node + 'static,
+{
+    let list = Node::fragment();
+    let owner = Scope::new();
+    let contexts = Contexts::capture();
+    let mut rows: Vec<Row<K>> = vec![];
+
+    effect({
+        let list = list.clone();
+        move || {
+            let items = items();
+            untrack(|| {
+                let mut old = HashMap::with_capacity(rows.len());
+                for row in rows.drain(..) {
+                    if let Some(duplicate) = old.insert(row.key, (row.node, row.scope)) {
+                        list.remove(&duplicate.0);
+                    }
+                }
+
+                for item in items {
+                    let key = key_fn(&item);
+                    let row = match old.remove(&key) {
+                        Some((node, scope)) => Row { key, node, scope },
+                        None => {
+                            let scope = owner.run(Scope::new);
+                            let node = scope.run(|| contexts.create(|| render_fn(item)));
+                            Row { key, node, scope }
+                        }
+                    };
+                    rows.push(row);
+                }
+
+                for (node, _) in old.into_values() {
+                    list.remove(&node);
+                }
+
+                for (i, row) in rows.iter().enumerate() {
+                    let current = list.children_ref().get(i).cloned();
+                    if current.as_ref() != Some(&row.node) {
+                        list.insert(&row.node, current.as_ref());
+                    }
+                }
+            });
+        }
+    });
+
+    list
+}
+
+/// Renders `then` while `cond` is true, and `fallback` otherwise.
+///
+/// See [`switch`] for details on when branches are rendered.
+pub fn show<C, T, E>(cond: C, then: T, fallback: E) -> Node
+where
+    C: FnMut() -> bool + 'static,
+    T: Fn() -> Node + 'static,
+    E: Fn() -> Node + 'static,
+{
+    switch(
+        cond,
+        move |&selected| if selected { then() } else { fallback() },
+    )
+}
+
+/// Renders the branch selected by `selector`.
+///
+/// `render` is only called again once `selector` returns a different value, at which point the previous branch
+/// is replaced and its scope disposed, running its unmount hooks. Changes to dependencies of `selector` that
+/// keep the same branch selected leave the rendered nodes untouched.
+///
+/// ```ignore
+/// switch(move || route.cloned(), |route| match route {
+///     Route::Home => home(),
+///     Route::About => about(),
+/// })
+/// ```
+pub fn switch<K, S, R>(mut selector: S, render: R) -> Node
+where
+    K: PartialEq + 'static,
+    S: FnMut() -> K + 'static,
+    R: Fn(&K) -> Node + 'static,
+{
+    let root = Node::fragment();
+    let owner = Scope::new();
+    let contexts = Contexts::capture();
+    let mut current: Option<(K, Node, Scope)> = None;
+
+    effect({
+        let root = root.clone();
+        move || {
+            let selected = selector();
+            if matches!(&current, Some((key, ..)) if *key == selected) {
+                return;
+            }
+
+            untrack(|| {
+                let scope = owner.run(Scope::new);
+                let node = scope.run(|| contexts.create(|| render(&selected)));
+                match current.take() {
+                    Some((_, prev, _)) => root.replace(&prev, &node),
+                    None => root.insert(&node, None),
+                }
+                current = Some((selected, node, scope));
+            });
+        }
+    });
+
+    root
+}
+
+/// Renders `f` only outside of the browser.
+///
+/// When hydrating, the server-rendered content is left in place as is, without being hydrated.
+pub fn server_only<F>(f: F) -> Node
+where
+    F: FnOnce() -> Node,
+{
+    let node = Node::create(NodeKind::ServerOnly);
+    if !is_browser() && !is_hydrating() {
+        node.insert(&f(), None);
+    }
+    node
+}
+
+/// Renders `f` only in the browser, with `fallback` rendered in its place outside of the browser.
+///
+/// When hydrating, the server-rendered fallback is discarded, and `f` is called once hydration has finished.
+pub fn client_only<F, E>(f: F, fallback: E) -> Node
+where
+    F: FnOnce() -> Node + 'static,
+    E: FnOnce() -> Node,
+{
+    if is_hydrating() {
+        return Node::create(NodeKind::ClientOnly(Some(Deferred::new(f))));
+    }
+
+    let node = Node::create(NodeKind::ClientOnly(None));
+    node.insert(&if is_browser() { f() } else { fallback() }, None);
+    node
+}
+
+/// The native node a [`portal`] mounts its content into.
+pub enum PortalTarget {
+    /// The element with the given id, which is also where the content is rendered outside of the browser.
+    Id(String),
+    /// A native node, which can only be used in the browser.
+    Native(NativeNode),
+}
+
+impl From<&str> for PortalTarget {
+    fn from(id: &str) -> Self {
+        Self::Id(id.to_string())
+    }
+}
+
+impl From<String> for PortalTarget {
+    fn from(id: String) -> Self {
+        Self::Id(id)
+    }
+}
+
+impl From<NativeNode> for PortalTarget {
+    fn from(node: NativeNode) -> Self {
+        Self::Native(node)
+    }
+}
+
+impl From<web_sys::Node> for PortalTarget {
+    fn from(node: web_sys::Node) -> Self {
+        Self::Native(node.into())
+    }
+}
+
+impl From<web_sys::Element> for PortalTarget {
+    fn from(element: web_sys::Element) -> Self {
+        Self::Native(element.into())
+    }
+}
+
+impl From<virt::Node> for PortalTarget {
+    fn from(node: virt::Node) -> Self {
+        Self::Native(node.into())
+    }
+}
+
+/// Mounts `children` into `target` rather than into the portal's parent.
+///
+/// The content remains part of the portal's logical tree, so it sees the same contexts and is disposed along
+/// with its owner. Outside of the browser, the content is rendered at the end of the element with the target's
+/// id, or at the end of the output if no such element is rendered. When hydrating, the server-rendered content
+/// is discarded, and `children` is called once hydration has finished.
+///
+/// ```ignore
+/// portal("modals", || div! {
+///     class => "modal";
+///     "Saved!";
+/// })
+/// ```
+///
+/// # Panics
+///
+/// Panics if the target is an id which no element in the document has, or if it is a native node outside of
+/// the browser.
+pub fn portal<T, F>(target: T, children: F) -> Node
+where
+    T: Into<PortalTarget>,
+    F: FnOnce() -> Node + 'static,
+{
+    let client = is_browser() || is_hydrating();
+    let (target, id) = match target.into() {
+        PortalTarget::Id(id) if client => {
+            let element = dom::document()
+                .get_element_by_id(&id)
+                .unwrap_or_else(|| panic!("portal target `#{id}` not found"));
+            (Some(element), Some(id))
+        }
+        PortalTarget::Id(id) => (None, Some(id)),
+        PortalTarget::Native(node) if client => (Some(node), None),
+        PortalTarget::Native(_) => {
+            panic!("native portal targets are only available in the browser")
+        }
+    };
+
+    if is_hydrating() {
+        return Node::create(NodeKind::Portal {
+            target,
+            id,
+            mounted: Cell::new(false),
+            deferred: Some(Deferred::new(children)),
+        });
+    }
+
+    let node = Node::create(NodeKind::Portal {
+        target,
+        id,
+        mounted: Cell::new(false),
+        deferred: None,
+    });
+    node.insert(&children(), None);
+    node
+}
+
+#[cfg(test)]
+mod tests {
+    use stardom_reactive::{run, signal, Input, Output};
+
+    use super::*;
+    use crate::render::{render_to_string, Mode};
+
+    fn text(node: &Node) -> String {
+        match node.kind() {
+            NodeKind::Text(content) => content.borrow().clone(),
+            // rendered rows and branches are wrapped in a frame of their own
+            NodeKind::Component(_) => text(&node.children()[0]),
+            _ => panic!("expected text node"),
+        }
+    }
+
+    #[test]
+    fn each_reuses_keyed_rows() {
+        run(|dispose| {
+            let items = signal(vec![1, 2, 3]);
+            let list = each(
+                move || items.cloned(),
+                |n| *n,
+                |n| Node::text(n.to_string()),
+            );
+            let before = list.children();
+
+            items.set(vec![3, 1, 4]);
+            let after = list.children();
+            assert_eq!(after.len(), 3);
+            assert!(after[0] == before[2]);
+            assert!(after[1] == before[0]);
+            assert_eq!(text(&after[2]), "4");
+
+            items.set(vec![]);
+            assert!(list.children().is_empty());
+
+            dispose();
+        });
+    }
+
+    #[test]
+    fn switch_only_renders_on_branch_change() {
+        run(|dispose| {
+            let count = signal(0);
+            let renders = signal(0);
+            let node = show(
+                move || count.get() < 10,
+                move || {
+                    renders.update(|n| *n += 1);
+                    Node::text("small".into())
+                },
+                || Node::text("large".into()),
+            );
+
+            count.set(1);
+            count.set(2);
+            assert_eq!(renders.get(), 1);
+            assert_eq!(text(&node.children()[0]), "small");
+
+            count.set(10);
+            assert_eq!(text(&node.children()[0]), "large");
+            assert_eq!(node.children().len(), 1);
+
+            dispose();
+        });
+    }
+
+    #[test]
+    fn environment_specific_nodes_are_marked() {
+        let html = render_to_string(Mode::HYDRATION, || {
+            [
+                server_only(|| Node::text("server".into())),
+                client_only(|| unreachable!(), || Node::text("loading".into())),
+            ]
+            .into_iter()
+            .collect()
+        });
+        assert_eq!(
+            html,
+            "<!--stardom:fragment-->\
+             <!--stardom:server-->server<!--/stardom:server-->\
+             <!--stardom:client-->loading<!--/stardom:client-->\
+             <!--/stardom:fragment-->"
+        );
+    }
+
+    #[test]
+    fn portals_render_into_their_target() {
+        let html = render_to_string(Mode::HYDRATION, || {
+            let modals = Node::element("div".into());
+            modals.set_attr("id".into(), "modals".into());
+            modals.insert(&Node::text("existing".into()), None);
+
+            [
+                modals,
+                portal("modals", || Node::text("modal".into())),
+                portal("missing", || Node::text("toast".into())),
+                Node::text("page".into()),
+            ]
+            .into_iter()
+            .collect()
+        });
+        assert_eq!(
+            html,
+            "<!--stardom:fragment-->\
+             <div id=\"modals\">existing<!--stardom:portal-->modal<!--/stardom:portal--></div>\
+             page<!--/stardom:fragment--><!--stardom:portal-->toast<!--/stardom:portal-->"
+        );
+    }
+}
@@ -0,0 +1,265 @@
+//! Elements placed in the document head by components.
+//!
+//! Head elements are mounted into `document.head` for as long as the node returned by a helper is part of the
+//! mounted tree. Elements with the same key, such as two `<title>`s or two `<meta name="description">`s, replace
+//! each other, with the most recently mounted one shown. When a keyed element is first mounted, existing
+//! elements with its key, such as those of the page template, are removed.
+//!
+//! Outside of the browser, head elements are not rendered into the body, but are collected by
+//! [`render_page`](crate::render::render_page) into a separate head string.
+
+use std::cell::Cell;
+
+use stardom_reactive::effect;
+
+use crate::{
+    dom::NativeNode,
+    env::is_hydrating,
+    node::{Deferred, Node, NodeKind},
+};
+
+/// Places the element returned by `children` in the document head, replacing other head elements with the same
+/// `key`.
+///
+/// When hydrating, `children` is called once hydration has finished, like the content of a
+/// [`portal`](crate::flow::portal).
+pub fn tag<F>(key: Option<String>, children: F) -> Node
+where
+    F: FnOnce() -> Node + 'static,
+{
+    if is_hydrating() {
+        return Node::create(NodeKind::Head {
+            key,
+            attached: Cell::new(false),
+            mounted: Cell::new(false),
+            deferred: Some(Deferred::new(children)),
+        });
+    }
+
+    let node = Node::create(NodeKind::Head {
+        key,
+        attached: Cell::new(false),
+        mounted: Cell::new(false),
+        deferred: None,
+    });
+    node.insert(&children(), None);
+    node
+}
+
+/// Sets the document's `<title>` to the value returned by `f`, updating it whenever a signal read by `f` changes.
+///
+/// ```ignore
+/// head::title(move || format!("{} unread", count.get()))
+/// ```
+pub fn title<F>(f: F) -> Node
+where
+    F: Fn() -> String + 'static,
+{
+    tag(Some("title".to_string()), move || {
+        let title = Node::element("title".to_string());
+        let text = Node::text(String::new());
+        title.insert(&text, None);
+        effect(move || text.set_text(f()));
+        title
+    })
+}
+
+/// Places a `<meta>` element with the given attributes in the document head.
+///
+/// Elements with the same `charset`, `name`, `property` or `http-equiv` replace each other.
+///
+/// ```ignore
+/// head::meta([("name", "description"), ("content", "A todo list")])
+/// ```
+pub fn meta<I, K, V>(attrs: I) -> Node
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: Into<String>,
+    V: Into<String>,
+{
+    void("meta", attrs)
+}
+
+/// Places a `<link>` element with the given attributes in the document head.
+///
+/// Canonical links replace each other, as do other links with the same `rel` and `href`.
+pub fn link<I, K, V>(attrs: I) -> Node
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: Into<String>,
+    V: Into<String>,
+{
+    void("link", attrs)
+}
+
+/// Places a `<script>` element with the given attributes and content in the document head.
+///
+/// Scripts with the same `src`, or otherwise the same `id`, replace each other.
+pub fn script<I, K, V>(attrs: I, content: String) -> Node
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: Into<String>,
+    V: Into<String>,
+{
+    let attrs = collect(attrs);
+    tag(key_of("script", &attrs), move || {
+        let script = element("script", attrs);
+        if !content.is_empty() {
+            script.insert(&Node::text(content), None);
+        }
+        script
+    })
+}
+
+fn void<I, K, V>(name: &'static str, attrs: I) -> Node
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: Into<String>,
+    V: Into<String>,
+{
+    let attrs = collect(attrs);
+    tag(key_of(name, &attrs), move || element(name, attrs))
+}
+
+fn collect<I, K, V>(attrs: I) -> Vec<(String, String)>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: Into<String>,
+    V: Into<String>,
+{
+    attrs
+        .into_iter()
+        .map(|(key, value)| (key.into(), value.into()))
+        .collect()
+}
+
+fn element(name: &str, attrs: Vec<(String, String)>) -> Node {
+    let element = Node::element(name.to_string());
+    for (key, value) in attrs {
+        element.set_attr(key, value);
+    }
+    element
+}
+
+fn key_of(name: &str, attrs: &[(String, String)]) -> Option<String> {
+    key(name, |attr| {
+        attrs
+            .iter()
+            .find(|(key, _)| key == attr)
+            .map(|(_, value)| value.clone())
+    })
+}
+
+/// Returns the key which head elements named `name` are deduplicated by, if any.
+fn key<F>(name: &str, attr: F) -> Option<String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    match name {
+        "title" => Some("title".to_string()),
+        "meta" => {
+            if attr("charset").is_some() {
+                return Some("meta:charset".to_string());
+            }
+            ["name", "property", "http-equiv"]
+                .into_iter()
+                .find_map(|key| attr(key).map(|value| format!("meta:{key}:{value}")))
+        }
+        "link" => match attr("rel")?.as_str() {
+            "canonical" => Some("link:canonical".to_string()),
+            rel => attr("href").map(|href| format!("link:{rel}:{href}")),
+        },
+        "script" => attr("src")
+            .map(|src| format!("script:{src}"))
+            .or_else(|| attr("id").map(|id| format!("script#{id}"))),
+        _ => None,
+    }
+}
+
+/// Removes the elements within `head` whose key is `key`.
+pub(crate) fn remove_existing(head: &NativeNode, key: &str) {
+    let mut child = head.first_child();
+    while let Some(node) = child {
+        child = node.next_sibling();
+        let matches = node.element_name().is_some_and(|name| {
+            self::key(&name.to_ascii_lowercase(), |attr| node.attribute(attr)).as_deref()
+                == Some(key)
+        });
+        if matches {
+            head.remove_child(&node);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use stardom_reactive::{signal, Input, Output, Signal};
+
+    use super::*;
+    use crate::{
+        browser::mount,
+        dom::virt::Document,
+        flow::show,
+        render::{render_page, Mode},
+    };
+
+    fn app(details: Signal<bool>) -> Node {
+        [
+            title(|| "Home".to_string()),
+            meta([("name", "description"), ("content", "home")]),
+            show(
+                move || details.get(),
+                || title(|| "Details".to_string()),
+                Node::fragment,
+            ),
+            Node::text("body".to_string()),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn render_collects_deduplicated_head() {
+        let page = render_page(Mode::empty(), || app(signal(true)));
+        assert_eq!(page.body, "body");
+        assert_eq!(
+            page.head,
+            "<meta name=\"description\" content=\"home\"><title>Details</title>"
+        );
+    }
+
+    #[test]
+    fn keyed_elements_replace_each_other() {
+        let document = Document::new();
+        document.head().set_inner_html("<title>Template</title>");
+
+        let details = Rc::new(Cell::new(None));
+        let _mount = mount(document.body(), {
+            let details = details.clone();
+            move || {
+                let signal = signal(false);
+                details.set(Some(signal));
+                app(signal)
+            }
+        });
+        let details = details.get().unwrap();
+        assert_eq!(
+            document.head().inner_html(),
+            "<title>Home</title><meta name=\"description\" content=\"home\">"
+        );
+
+        details.set(true);
+        assert_eq!(
+            document.head().inner_html(),
+            "<meta name=\"description\" content=\"home\"><title>Details</title>"
+        );
+
+        details.set(false);
+        assert_eq!(
+            document.head().inner_html(),
+            "<meta name=\"description\" content=\"home\"><title>Home</title>"
+        );
+    }
+}
@@ -8,7 +8,15 @@ pub mod __macro;
 pub mod component;
 
 pub mod attrs;
+pub mod dom;
+pub mod error;
 pub mod events;
+pub mod flow;
+pub mod head;
+pub mod props;
+pub mod router;
+pub mod state;
+pub mod suspense;
 pub mod util;
 
 mod env;
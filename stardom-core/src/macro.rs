@@ -4,19 +4,20 @@ pub mod bindings {
     use stardom_reactive::Output;
     use wasm_bindgen::JsCast;
 
-    use crate::{events::EventOptions, node::Node};
+    use crate::{dom::NativeNode, events::EventOptions, node::Node};
 
     pub fn bind_value<O>(node: &Node, output: O)
     where
         O: Output<String> + 'static,
     {
         node.event(&"input", EventOptions::new(), move |ev| {
-            let value = ev
-                .current_target()
-                .unwrap()
-                .unchecked_into::<web_sys::HtmlInputElement>()
-                .value();
+            let value = match ev.current_target().unwrap() {
+                NativeNode::Web(node) => node.unchecked_into::<web_sys::HtmlInputElement>().value(),
+                // virtual inputs reflect their value to the attribute
+                NativeNode::Virtual(node) => node.attribute("value").unwrap_or_default(),
+            };
             output.set(value);
-        });
+        })
+        .forget();
     }
 }
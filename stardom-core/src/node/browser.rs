@@ -1,16 +1,58 @@
-use std::mem;
+use std::{fmt, mem};
 
 use crate::{
+    dom::{self, Document, NativeNode},
     env::{self, Env},
-    node::Node,
+    node::{delegate, Node},
 };
 
-pub fn mount<N, F>(root: N, f: F)
+/// Options for [`mount_with`].
+#[derive(Clone, Copy, Default, Debug)]
+pub struct MountOptions {
+    pub delegate: bool,
+}
+
+impl MountOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles common bubbling events like `click` with a single listener on `root`, rather than adding a
+    /// listener to each element.
+    ///
+    /// Delegated handlers are invoked once the event has bubbled up to the root, in the order the event would
+    /// have reached them. Content of portals and the head is handled by listeners on their targets instead. The
+    /// browser's event has the root as its `currentTarget`, while [`Event::current_target`] still returns the
+    /// handler's element.
+    ///
+    /// [`Event::current_target`]: crate::events::Event::current_target
+    pub fn delegate(mut self, value: bool) -> Self {
+        self.delegate = value;
+        self
+    }
+}
+
+/// Mounts the node returned by `f` into `root`.
+///
+/// `root` may be a node of the browser's DOM, or of a [virtual](crate::dom::virt) DOM outside of the browser.
+/// See [`Mount`] for how long the latter stays current.
+pub fn mount<N, F>(root: N, f: F) -> Mount
+where
+    N: Into<NativeNode>,
+    F: FnOnce() -> Node,
+{
+    mount_with(root, MountOptions::new(), f)
+}
+
+/// Like [`mount`], but with the given options.
+pub fn mount_with<N, F>(root: N, options: MountOptions, f: F) -> Mount
 where
-    N: Into<web_sys::Node>,
+    N: Into<NativeNode>,
     F: FnOnce() -> Node,
 {
-    env::replace(Env::Browser);
+    let root = root.into();
+    let mount = Mount::enter(&root, Env::Browser);
+    delegate::set_root(&root, options.delegate);
     stardom_reactive::run(|_| {
         let node = Node::fragment();
         node.manual_bind(root);
@@ -18,4 +60,43 @@ where
         node.set_main_tree(true);
         mem::forget(node);
     });
+    mount
+}
+
+/// Keeps the document of a [mounted](mount) or [hydrated](crate::hydrate::hydrate) root current.
+///
+/// Nodes created after mounting, such as by effects or event handlers, are created by the current document.
+/// For roots in the browser's DOM, which is the current document by default, this guard does nothing and can
+/// be dropped right away. For roots in a [virtual](crate::dom::virt) DOM, it has to be held for as long as the
+/// mounted nodes are updated, and dropping it restores the document and environment which were current before.
+pub struct Mount {
+    prev: Option<(Option<Document>, Env)>,
+}
+
+impl Mount {
+    /// Makes the document of `root` current, and enters `env`.
+    pub(crate) fn enter(root: &NativeNode, env: Env) -> Self {
+        let document = dom::replace_document(root);
+        let env = env::replace(env);
+        Self {
+            prev: matches!(root, NativeNode::Virtual(_)).then_some((document, env)),
+        }
+    }
+}
+
+impl fmt::Debug for Mount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Mount")
+            .field("virtual", &self.prev.is_some())
+            .finish()
+    }
+}
+
+impl Drop for Mount {
+    fn drop(&mut self) {
+        if let Some((document, env)) = self.prev.take() {
+            dom::restore_document(document);
+            env::replace(env);
+        }
+    }
 }
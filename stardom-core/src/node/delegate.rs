@@ -0,0 +1,307 @@
+//! Delegation of events to the mount root.
+//!
+//! Rather than adding a listener to each element, handlers of [delegated](DELEGATED) events are stored on their
+//! node, and a single listener per event is added to the root. That listener walks from the event's target up to
+//! the root, invoking the handlers of each node along the way.
+//!
+//! Portals and head content are mounted outside of the root, so their targets are listened on as well, each
+//! handling the events of the nodes below it up to the next root.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    rc::Weak,
+    thread_local,
+};
+
+use crate::{
+    dom::{Listener, NativeEvent, NativeNode},
+    events::EventOptions,
+    node::{Node, RawNode},
+};
+
+/// Events which bubble and are commonly handled on many elements.
+const DELEGATED: &[&str] = &[
+    "auxclick",
+    "beforeinput",
+    "change",
+    "click",
+    "contextmenu",
+    "dblclick",
+    "focusin",
+    "focusout",
+    "input",
+    "keydown",
+    "keyup",
+    "mousedown",
+    "mousemove",
+    "mouseout",
+    "mouseover",
+    "mouseup",
+    "pointerdown",
+    "pointermove",
+    "pointerout",
+    "pointerover",
+    "pointerup",
+    "submit",
+    "touchend",
+];
+
+struct Delegation {
+    /// The mount root, followed by the targets of portals and head content.
+    roots: Vec<Root>,
+    /// The events and phases listened to on every root.
+    listening: HashSet<(String, bool)>,
+}
+
+struct Root {
+    native: NativeNode,
+    listeners: Vec<Listener>,
+}
+
+impl Root {
+    fn listen(&mut self, name: &str, capture: bool) {
+        let listener = Listener::new({
+            let name = name.to_string();
+            let root = self.native.clone();
+            move |ev| dispatch(&root, &name, capture, ev)
+        });
+        self.native
+            .add_event_listener(name, &listener, EventOptions::new().capture(capture));
+        self.listeners.push(listener);
+    }
+}
+
+thread_local! {
+    static DELEGATION: RefCell<Option<Delegation>> = const { RefCell::new(None) };
+    static NODES: RefCell<HashMap<u64, Weak<RawNode>>> = RefCell::default();
+    static NEXT_KEY: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Delegates events to `root` if `enabled`, for the nodes created from now on.
+pub(crate) fn set_root(root: &NativeNode, enabled: bool) {
+    DELEGATION.set(enabled.then(|| Delegation {
+        roots: vec![Root {
+            native: root.clone(),
+            listeners: vec![],
+        }],
+        listening: HashSet::new(),
+    }));
+}
+
+/// Also delegates events to `target`, which content is mounted into outside of the root.
+pub(crate) fn add_root(target: &NativeNode) {
+    DELEGATION.with_borrow_mut(|delegation| {
+        let Some(delegation) = delegation else {
+            return;
+        };
+        if delegation.roots.iter().any(|root| root.native == *target) {
+            return;
+        }
+
+        let mut root = Root {
+            native: target.clone(),
+            listeners: vec![],
+        };
+        for (name, capture) in &delegation.listening {
+            root.listen(name, *capture);
+        }
+        delegation.roots.push(root);
+    });
+}
+
+/// Returns whether handlers of the event `name` are delegated to the root.
+pub(crate) fn is_delegated(name: &str) -> bool {
+    DELEGATED.contains(&name) && DELEGATION.with_borrow(Option::is_some)
+}
+
+/// Routes events `name` targeting `native` to the handlers of `node`.
+pub(crate) fn attach(node: &Node, native: &NativeNode, name: &str, capture: bool) {
+    let key = native.delegate_key().unwrap_or_else(|| {
+        let key = NEXT_KEY.replace(NEXT_KEY.get() + 1);
+        native.set_delegate_key(key);
+        key
+    });
+    NODES.with_borrow_mut(|nodes| nodes.insert(key, node.downgrade()));
+
+    DELEGATION.with_borrow_mut(|delegation| {
+        let delegation = delegation.as_mut().expect("events are not delegated");
+        if delegation.listening.insert((name.to_string(), capture)) {
+            for root in &mut delegation.roots {
+                root.listen(name, capture);
+            }
+        }
+    });
+}
+
+/// Stops routing events to the node of `native`, once it no longer has delegated handlers.
+pub(crate) fn detach(native: &NativeNode) {
+    if let Some(key) = native.delegate_key() {
+        // the nodes may already be gone while the thread exits
+        let _ = NODES.try_with(|nodes| nodes.borrow_mut().remove(&key));
+    }
+}
+
+/// Invokes the handlers for `ev` from the root down to its target when capturing, or from the target up to the
+/// root otherwise, until propagation is stopped.
+///
+/// Events are left to the nearest root above their target, so that content mounted into another root's subtree
+/// is not handled twice.
+fn dispatch(root: &NativeNode, name: &str, capture: bool, ev: NativeEvent) {
+    let roots = DELEGATION.with_borrow(|delegation| {
+        delegation.as_ref().map_or(vec![], |delegation| {
+            delegation
+                .roots
+                .iter()
+                .map(|root| root.native.clone())
+                .collect::<Vec<_>>()
+        })
+    });
+
+    let mut path = vec![];
+    let mut current = ev.target();
+    loop {
+        let Some(native) = current else {
+            return;
+        };
+        if roots.contains(&native) {
+            if native != *root {
+                return;
+            }
+            break;
+        }
+        if let Some(node) = native.delegate_key().and_then(lookup) {
+            path.push(node);
+        }
+        current = native.parent_node();
+    }
+    if capture {
+        path.reverse();
+    }
+
+    for node in path {
+        if ev.propagation_stopped() {
+            break;
+        }
+        node.invoke_delegated(name, capture, &ev);
+    }
+}
+
+fn lookup(key: u64) -> Option<Node> {
+    NODES.with_borrow_mut(|nodes| {
+        let node = nodes.get(&key)?.upgrade();
+        if node.is_none() {
+            nodes.remove(&key);
+        }
+        node.map(Node)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::{
+        browser::{mount_with, MountOptions},
+        dom::virt::{Document, Event},
+    };
+
+    #[test]
+    fn delegated_handlers_follow_propagation() {
+        let document = Document::new();
+        let log = Rc::new(RefCell::new(vec![]));
+        let stop = Rc::new(Cell::new(false));
+        let ev = Event::new("click").bubbles(true);
+
+        let _mount = mount_with(document.body(), MountOptions::new().delegate(true), || {
+            let handler = |name: &'static str| {
+                let log = log.clone();
+                move |_| log.borrow_mut().push(name)
+            };
+
+            let list = Node::element("ul".into());
+            list.event(&"click", EventOptions::new(), handler("list"))
+                .forget();
+            list.event(
+                &"click",
+                EventOptions::new().capture(true),
+                handler("capture"),
+            )
+            .forget();
+            for id in ["a", "b"] {
+                let row = Node::element("li".into());
+                row.event(&"click", EventOptions::new(), handler(id))
+                    .forget();
+                list.insert(&row, None);
+            }
+
+            let span = Node::element("span".into());
+            span.set_attr("id".into(), "target".into());
+            span.event(&"click", EventOptions::new(), {
+                let (log, stop, ev) = (log.clone(), stop.clone(), ev.clone());
+                move |_| {
+                    log.borrow_mut().push("span");
+                    if stop.get() {
+                        ev.stop_propagation();
+                    }
+                }
+            })
+            .forget();
+            list.children()[0].insert(&span, None);
+            list
+        });
+
+        let target = document.get_element_by_id("target").unwrap();
+        target.dispatch_event(&ev);
+        assert_eq!(*log.borrow(), ["capture", "span", "a", "list"]);
+
+        log.borrow_mut().clear();
+        stop.set(true);
+        target.dispatch_event(&ev);
+        assert_eq!(*log.borrow(), ["capture", "span"]);
+    }
+
+    #[test]
+    fn portal_content_is_delegated_once() {
+        let document = Document::new();
+        let root = document.create_element("div");
+        document.body().append_child(&root);
+        let clicks = Rc::new(RefCell::new(vec![]));
+
+        let _mount = mount_with(root.clone(), MountOptions::new().delegate(true), || {
+            let clicks = clicks.clone();
+            let button = move |id: &'static str| {
+                let button = Node::element("button".into());
+                button.set_attr("id".into(), id.into());
+                let clicks = clicks.clone();
+                button
+                    .event(&"click", EventOptions::new(), move |_| {
+                        clicks.borrow_mut().push(id)
+                    })
+                    .forget();
+                button
+            };
+
+            let node = Node::fragment();
+            node.insert(&button("inside"), None);
+            let portal = crate::flow::portal(document.body(), {
+                let button = button.clone();
+                move || button("portal")
+            });
+            node.insert(&portal, None);
+
+            // handlers of dropped nodes are no longer routed to
+            let dropped = button("dropped");
+            drop(dropped);
+            node
+        });
+
+        for id in ["inside", "portal"] {
+            let button = document.get_element_by_id(id).unwrap();
+            button.dispatch_event(&Event::new("click").bubbles(true));
+        }
+        assert_eq!(*clicks.borrow(), ["inside", "portal"]);
+        assert_eq!(NODES.with_borrow(HashMap::len), 2);
+    }
+}
@@ -1,34 +1,170 @@
-use std::mem;
+use std::{error, fmt, mem};
 
-use wasm_bindgen::prelude::*;
+use stardom_reactive::{untrack, Input};
 
 use crate::{
+    dom::{self, NativeNode},
     env::{self, Env},
-    node::{Node, NodeKind},
-    util::document,
+    node::{browser::Mount, delegate, markers, Node, NodeKind},
+    render,
+    state::{self, Transfer},
 };
 
-pub fn hydrate<F>(root: web_sys::Node, f: F)
+/// Options for [`hydrate_with`].
+#[derive(Clone, Copy, Default, Debug)]
+pub struct HydrateOptions {
+    pub recover: bool,
+    pub delegate: bool,
+}
+
+impl HydrateOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Recovers from mismatches by discarding the server-rendered children of the nearest element enclosing the
+    /// mismatch, and creating that element's children in the browser instead.
+    pub fn recover(mut self, value: bool) -> Self {
+        self.recover = value;
+        self
+    }
+
+    /// Delegates common bubbling events to the root, like
+    /// [`MountOptions::delegate`](crate::browser::MountOptions::delegate).
+    pub fn delegate(mut self, value: bool) -> Self {
+        self.delegate = value;
+        self
+    }
+}
+
+/// A mismatch between the server-rendered HTML and the node tree being hydrated.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct HydrationError {
+    /// The child indices leading from the root to the mismatched native node, or to where the expected node
+    /// was missing.
+    pub path: Vec<usize>,
+    pub expected: Expected,
+    /// A description of the native node found, or `None` if there was no node left.
+    pub found: Option<String>,
+}
+
+impl fmt::Display for HydrationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "hydration mismatch at {:?}: expected {}, ",
+            self.path, self.expected
+        )?;
+        match &self.found {
+            Some(found) => write!(f, "found {found}"),
+            None => write!(f, "found nothing"),
+        }
+    }
+}
+
+impl error::Error for HydrationError {}
+
+/// The native node expected by the hydrator.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Expected {
+    Element(String),
+    Text,
+    /// A comment marker delimiting a section.
+    Marker(String),
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Element(name) => write!(f, "<{name}>"),
+            Self::Text => write!(f, "text"),
+            Self::Marker(marker) => write!(f, "`{marker}` marker"),
+        }
+    }
+}
+
+/// Hydrates the server-rendered content of `root` with the node returned by `f`.
+///
+/// Like [`mount`](super::browser::mount), `root` may be a node of a [virtual](crate::dom::virt) DOM, which
+/// stays current for as long as the returned [`Mount`] is held.
+///
+/// # Panics
+///
+/// Panics if the server-rendered content does not match the node tree. See [`hydrate_with`] for recovering
+/// from mismatches instead.
+pub fn hydrate<N, F>(root: N, f: F) -> Mount
 where
+    N: Into<NativeNode>,
     F: FnOnce() -> Node,
 {
-    env::replace(Env::Hydrate);
+    match hydrate_with(root, HydrateOptions::new(), f) {
+        Ok((mount, _)) => mount,
+        Err(error) => panic!("{error}"),
+    }
+}
 
-    let root_node = Node::fragment();
-    let provided = f();
-    root_node.insert(&provided, None);
+/// Like [`hydrate`], but returns the first mismatch as an error.
+///
+/// If [`HydrateOptions::recover`] is set, hydration never fails, and the mismatches which were recovered from
+/// are returned along with the [`Mount`] instead.
+///
+/// # Errors
+///
+/// On a mismatch, the node tree is disposed and nothing is mounted, but the content of `root` is not restored.
+/// It is left hydrated up to the mismatch: the markers, whitespace and discarded sections before it have been
+/// removed, hydrated text nodes hold the text of the node tree, and the listeners added to hydrated elements no
+/// longer call any handler. The transferred state has been removed from the document as well, and the previous
+/// document is current again. To render the content in the browser instead, clear `root` and
+/// [`mount`](super::browser::mount) into it.
+pub fn hydrate_with<N, F>(
+    root: N,
+    options: HydrateOptions,
+    f: F,
+) -> Result<(Mount, Vec<HydrationError>), HydrationError>
+where
+    N: Into<NativeNode>,
+    F: FnOnce() -> Node,
+{
+    let root = root.into();
+    let mount = Mount::enter(&root, Env::Hydrate);
+    delegate::set_root(&root, options.delegate);
 
-    let mut hydrator = Hydrator::new(root.clone());
-    hydrator.hydrate(&provided);
+    stardom_reactive::run(|dispose| {
+        state::replace(state::load());
+        let root_node = Node::fragment();
+        let provided = f();
+        state::replace(Transfer::default());
+        root_node.insert(&provided, None);
 
-    env::replace(Env::Browser);
+        let mut hydrator = Hydrator::new(root.clone(), options);
+        let result = hydrator
+            .hydrate(&provided)
+            .or_else(|error| hydrator.recover(error, &root_node, &root));
 
-    root_node.manual_bind(root);
-    root_node.set_main_tree(true);
-    mem::forget(root_node);
-}
+        env::replace(Env::Browser);
+
+        if let Err(error) = result {
+            drop(root_node);
+            dispose();
+            return Err(error);
+        }
 
-const MARKER: &str = "stardom:raw";
+        // portals whose target was not rendered are rendered at the end of the output
+        remove_portal_sections(&root);
+        remove_sections(
+            &dom::document().head(),
+            markers::HEAD_START,
+            markers::HEAD_END,
+        );
+        root_node.manual_bind(root);
+        for node in hydrator.deferred {
+            create_deferred(&node);
+        }
+        root_node.set_main_tree(true);
+        mem::forget(root_node);
+        Ok((mount, hydrator.errors))
+    })
+}
 
 #[derive(Clone, Copy)]
 enum Step {
@@ -38,163 +174,719 @@ enum Step {
 use Step::*;
 
 struct Hydrator {
-    tree: web_sys::TreeWalker,
-    parent: web_sys::Node,
+    tree: Walker,
+    parent: NativeNode,
+    options: HydrateOptions,
+    /// Client-only, suspense and portal nodes whose content is created or mounted once hydration has finished.
+    deferred: Vec<Node>,
+    /// Mismatches which were recovered from.
+    errors: Vec<HydrationError>,
 }
 
 impl Hydrator {
-    fn new(root: web_sys::Node) -> Self {
+    fn new(root: NativeNode, options: HydrateOptions) -> Self {
         Self {
-            tree: document().create_tree_walker(&root).unwrap(),
+            tree: Walker::new(root.clone()),
             parent: root,
+            options,
+            deferred: vec![],
+            errors: vec![],
         }
     }
 
-    fn hydrate(&mut self, node: &Node) {
+    fn hydrate(&mut self, node: &Node) -> Result<(), HydrationError> {
         match node.kind() {
             NodeKind::Element { name, .. } => {
                 self.skip_whitespace();
-                let native = self.step_element();
-                let prev = mem::replace(&mut self.parent, native.clone().into());
-
-                for event in node.browser().events.borrow().values() {
-                    native
-                        .add_event_listener_with_callback_and_add_event_listener_options(
-                            &event.name,
-                            event.closure.as_ref().unchecked_ref(),
-                            &event.options.to_native(name),
-                        )
-                        .unwrap();
+                let native = self.step_element(name)?;
+                remove_portal_sections(&native);
+                let prev = mem::replace(&mut self.parent, native.clone());
+
+                node.attach_events(&native);
+                node.kind().apply_props(&native);
+
+                // text-only elements are rendered without markers, so their text cannot be told apart
+                if render::is_text_only(name, node.element_namespace()) {
+                    self.parent = prev;
+                    self.replace_children(node, &native);
+                    node.manual_bind(native);
+                    return Ok(());
                 }
 
-                for child in &*node.children_ref() {
-                    self.hydrate(child);
+                let result = node
+                    .children_ref()
+                    .iter()
+                    .try_for_each(|child| self.hydrate(child))
+                    .map(|()| self.skip_trailing_whitespace());
+                self.parent = prev;
+                if let Err(error) = result {
+                    self.recover(error, node, &native)?;
                 }
                 node.manual_bind(native);
-                self.parent = prev;
             }
             NodeKind::Text(content) => {
-                let native = self.step_text();
-                native.set_text_content(Some(&content.borrow()));
+                let native = self.step_text(content.borrow().is_empty())?;
+                native.set_text_content(&content.borrow());
                 node.manual_bind(native);
             }
             NodeKind::Raw(_) => {
-                self.skip_whitespace();
-                self.step_marker();
-
-                loop {
-                    if is_marker(&self.peek(Over)) {
-                        self.consume(Over);
-                        break;
+                self.adopt_section(node, markers::RAW, markers::RAW)?;
+            }
+            NodeKind::Fragment => {
+                self.hydrate_section(node, markers::FRAGMENT_START, markers::FRAGMENT_END)?;
+            }
+            NodeKind::Component(component) if component.transparent => {
+                for child in &*node.children_ref() {
+                    self.hydrate(child)?;
+                }
+            }
+            NodeKind::Component(_) => {
+                self.hydrate_section(node, markers::COMPONENT_START, markers::COMPONENT_END)?;
+            }
+            NodeKind::ServerOnly => {
+                // server-only content is kept exactly as rendered, without being hydrated
+                self.adopt_section(node, markers::SERVER_START, markers::SERVER_END)?;
+            }
+            NodeKind::ClientOnly(_) => {
+                self.discard_section(node, markers::CLIENT_START, markers::CLIENT_END)?;
+            }
+            NodeKind::Suspense { pending, content } => {
+                // a section is only hydrated if the server rendered the same content the browser shows
+                if untrack(|| pending.get()) == 0 && self.peek_resolved() {
+                    self.step_marker(markers::SUSPENSE_START)?;
+                    self.step_marker(markers::SUSPENSE_RESOLVED)?;
+                    for child in &*node.children_ref() {
+                        self.hydrate(child)?;
                     }
-                    let native = self.step(Over);
+                    self.step_marker(markers::SUSPENSE_END)?;
+                } else {
+                    self.discard_section(node, markers::SUSPENSE_START, markers::SUSPENSE_END)?;
+                    self.create_natives(node);
+                    // the content is swapped in once resolved
+                    self.create_natives(content);
+                }
+            }
+            NodeKind::Portal { mounted, .. } => {
+                // the content has no native nodes at the portal's position, and is created within the target
+                mounted.set(true);
+                self.defer(node);
+            }
+            NodeKind::Head { .. } => {
+                // the content is created and mounted into the head once the node is part of the main tree
+                self.defer(node);
+            }
+        }
+        Ok(())
+    }
+
+    /// Recovers from `error` if enabled, by [replacing the children](Self::replace_children) of `native`.
+    fn recover(
+        &mut self,
+        error: HydrationError,
+        node: &Node,
+        native: &NativeNode,
+    ) -> Result<(), HydrationError> {
+        if !self.options.recover {
+            return Err(error);
+        }
+        self.errors.push(error);
+        self.replace_children(node, native);
+        Ok(())
+    }
+
+    /// Replaces the server-rendered children of `native` with `node`'s children created in the browser.
+    fn replace_children(&mut self, node: &Node, native: &NativeNode) {
+        while let Some(child) = native.first_child() {
+            native.remove_child(&child);
+        }
+        for child in &*node.children_ref() {
+            self.create_natives(child);
+            child.mount(native, None);
+        }
+
+        // continue after the recreated children
+        let mut last = native.clone();
+        while let Some(child) = last.last_child() {
+            last = child;
+        }
+        self.tree.current = last;
+    }
+
+    /// Creates the native nodes of `node` and its descendants which were not hydrated.
+    fn create_natives(&mut self, node: &Node) {
+        match node.kind() {
+            NodeKind::Element { .. } | NodeKind::Text(_) if node.native_node_ref().is_none() => {
+                let native = node.kind().create_native().unwrap();
+                node.attach_events(&native);
+                for child in &*node.children_ref() {
+                    self.create_natives(child);
+                    child.mount(&native, None);
+                }
+                node.manual_bind(native);
+            }
+            NodeKind::Element { .. } | NodeKind::Text(_) => {}
+            NodeKind::Raw(content) if node.children_ref().is_empty() => {
+                for native in dom::document().parse_fragment(&content.borrow()) {
                     let holder = Node::fragment();
                     node.insert(&holder, None);
                     holder.manual_bind(native);
                 }
             }
+            NodeKind::Raw(_) | NodeKind::ServerOnly => {}
             NodeKind::Fragment | NodeKind::Component(_) => {
                 for child in &*node.children_ref() {
-                    self.hydrate(child);
+                    self.create_natives(child);
                 }
             }
+            NodeKind::Suspense { .. } => {
+                for child in &*node.children_ref() {
+                    self.create_natives(child);
+                }
+                self.defer(node);
+            }
+            NodeKind::ClientOnly(_) | NodeKind::Head { .. } => self.defer(node),
+            NodeKind::Portal { mounted, .. } => {
+                mounted.set(true);
+                self.defer(node);
+            }
+        }
+    }
+
+    fn defer(&mut self, node: &Node) {
+        if !self.deferred.contains(node) {
+            self.deferred.push(node.clone());
+        }
+    }
+
+    /// Hydrates the children of `node` between a `start` and `end` marker, removing both markers.
+    fn hydrate_section(
+        &mut self,
+        node: &Node,
+        start: &str,
+        end: &str,
+    ) -> Result<(), HydrationError> {
+        self.step_marker(start)?;
+        for child in &*node.children_ref() {
+            self.hydrate(child)?;
         }
+        self.step_marker(end)
+    }
+
+    /// Returns whether the next nodes are the start of a suspense section which was rendered resolved.
+    fn peek_resolved(&mut self) -> bool {
+        self.skip_whitespace();
+        let current = self.tree.current.clone();
+        let resolved = self
+            .try_step(Next)
+            .is_some_and(|start| is_marker(&start, markers::SUSPENSE_START))
+            && self
+                .try_step(Next)
+                .is_some_and(|next| is_marker(&next, markers::SUSPENSE_RESOLVED));
+        self.tree.current = current;
+        resolved
     }
 
-    fn try_step(&mut self, method: Step) -> Option<web_sys::Node> {
+    /// Binds each native node within a marked section to a holder fragment within `node`.
+    fn adopt_section(&mut self, node: &Node, start: &str, end: &str) -> Result<(), HydrationError> {
+        for native in self.step_section(start, end)? {
+            let holder = Node::fragment();
+            node.insert(&holder, None);
+            holder.manual_bind(native);
+        }
+        Ok(())
+    }
+
+    /// Removes a marked section, deferring the creation of `node`'s content until hydration has finished.
+    ///
+    /// The server either rendered a fallback, or content which the client has no means to reproduce yet, so
+    /// the section is replaced once the content has been created in the browser.
+    fn discard_section(
+        &mut self,
+        node: &Node,
+        start: &str,
+        end: &str,
+    ) -> Result<(), HydrationError> {
+        let section = self.step_section(start, end)?;
+        if let Some(first) = section.first() {
+            self.tree.current = first.clone();
+            self.tree.previous_node().unwrap();
+        }
+        for native in section {
+            native.parent_node().unwrap().remove_child(&native);
+        }
+        self.defer(node);
+        Ok(())
+    }
+
+    /// Steps over the sibling nodes between a `start` and `end` marker, removing both markers.
+    ///
+    /// Sections with distinct markers may be nested, in which case the inner markers are treated as content.
+    fn step_section(&mut self, start: &str, end: &str) -> Result<Vec<NativeNode>, HydrationError> {
+        self.skip_whitespace();
+        let marker = self
+            .try_step(Next)
+            .filter(|marker| is_marker(marker, start))
+            .ok_or_else(|| self.mismatch(Expected::Marker(start.to_string())))?;
+
+        let mut depth = 0;
+        let mut nodes = vec![];
+        loop {
+            let Some(next) = self.try_peek(Over) else {
+                return Err(self.mismatch(Expected::Marker(end.to_string())));
+            };
+            if is_marker(&next, end) {
+                if depth == 0 {
+                    self.consume(Over);
+                    break;
+                }
+                depth -= 1;
+            } else if start != end && is_marker(&next, start) {
+                depth += 1;
+            }
+            nodes.push(self.step(Over));
+        }
+
+        // the walker must not be left on a detached node
+        if marker == self.tree.current {
+            self.tree.previous_node().unwrap();
+        }
+        marker.parent_node().unwrap().remove_child(&marker);
+
+        Ok(nodes)
+    }
+
+    fn try_step(&mut self, method: Step) -> Option<NativeNode> {
         match method {
-            Step::Next => self.tree.next_node().unwrap(),
-            Step::Over => self
-                .tree
-                .next_sibling()
-                .unwrap()
-                .or_else(|| self.tree.next_node().unwrap()),
+            Step::Next => self.tree.next_node(),
+            Step::Over => self.tree.next_sibling().or_else(|| self.tree.next_node()),
         }
     }
 
-    fn try_peek(&mut self, method: Step) -> Option<web_sys::Node> {
-        let current = self.tree.current_node();
+    fn try_peek(&mut self, method: Step) -> Option<NativeNode> {
+        let current = self.tree.current.clone();
         let next = self.try_step(method);
-        self.tree.set_current_node(&current);
+        self.tree.current = current;
         next
     }
 
-    fn step(&mut self, method: Step) -> web_sys::Node {
+    fn step(&mut self, method: Step) -> NativeNode {
         self.try_step(method).expect("hydrator reached section end")
     }
 
-    fn peek(&mut self, method: Step) -> web_sys::Node {
-        self.try_peek(method).expect("hydrator reached section end")
-    }
-
-    fn consume(&mut self, method: Step) -> web_sys::Node {
-        let current = self.tree.current_node();
+    fn consume(&mut self, method: Step) -> NativeNode {
+        let current = self.tree.current.clone();
         let node = self.step(method);
-        node.parent_node().unwrap().remove_child(&node).unwrap();
-        self.tree.set_current_node(&current);
+        node.parent_node().unwrap().remove_child(&node);
+        self.tree.current = current;
         node
     }
 
     fn skip_whitespace(&mut self) {
-        while self.peek(Next).has_type::<web_sys::Text>() {
+        while self.try_peek(Next).is_some_and(|node| node.is_text()) {
             self.consume(Next);
         }
     }
 
-    fn step_element(&mut self) -> web_sys::Element {
-        self.step(Next)
-            .dyn_into()
-            .unwrap_or_else(|node| mismatch("element", &node))
+    /// Removes the whitespace which pretty rendering inserts before the end tag of the current parent.
+    fn skip_trailing_whitespace(&mut self) {
+        while let Some(next) = self
+            .next_child()
+            .filter(|next| next.is_text() && next.text_content().trim_ascii().is_empty())
+        {
+            self.parent.remove_child(&next);
+        }
+    }
+
+    fn step_element(&mut self, name: &str) -> Result<NativeNode, HydrationError> {
+        self.try_step(Next)
+            .filter(|node| {
+                node.element_name()
+                    .is_some_and(|found| found.eq_ignore_ascii_case(name))
+            })
+            .ok_or_else(|| self.mismatch(Expected::Element(name.to_string())))
+    }
+
+    /// Removes the `marker` comment following the current node.
+    fn step_marker(&mut self, marker: &str) -> Result<(), HydrationError> {
+        self.skip_whitespace();
+        if self
+            .try_peek(Next)
+            .is_some_and(|next| is_marker(&next, marker))
+        {
+            self.consume(Next);
+            Ok(())
+        } else {
+            self.tree.next_node();
+            Err(self.mismatch(Expected::Marker(marker.to_string())))
+        }
     }
 
-    fn step_text(&mut self) -> web_sys::Text {
-        if let Some(peeked) = self
-            .try_peek(Step::Next)
-            .and_then(|node| node.dyn_into::<web_sys::Text>().ok())
+    /// Steps to the next text node, or creates one if `empty`, as empty text nodes are not rendered.
+    fn step_text(&mut self, empty: bool) -> Result<NativeNode, HydrationError> {
+        if empty {
+            let text = dom::document().create_text("");
+            self.parent.insert_before(&text, self.next_child().as_ref());
+            self.tree.current = text.clone();
+            return Ok(text);
+        }
+
+        // adjacent text nodes are separated by a marker
+        if self
+            .try_peek(Next)
+            .is_some_and(|next| is_marker(&next, markers::TEXT))
         {
-            // guards for the condition where the next node is text, but exists outside of the current element:
-            // div! {
-            //   (current)
-            // }
-            // "B" << peeked
-            if self.parent.contains(Some(&peeked)) {
-                self.step(Next);
-                return peeked;
+            self.consume(Next);
+        }
+
+        // the next node may be text following the current element:
+        // div! {
+        //   (current)
+        // }
+        // "B" << peeked
+        self.try_step(Next)
+            .filter(|next| next.is_text() && self.parent.contains(next))
+            .ok_or_else(|| self.mismatch(Expected::Text))
+    }
+
+    /// Returns the child of the current parent following the current node.
+    fn next_child(&self) -> Option<NativeNode> {
+        let mut current = self.tree.current.clone();
+        if current == self.parent {
+            return self.parent.first_child();
+        }
+        while current.parent_node().as_ref() != Some(&self.parent) {
+            current = current.parent_node().unwrap();
+        }
+        current.next_sibling()
+    }
+
+    /// Creates an error for a mismatch with the node the walker was just moved to.
+    fn mismatch(&self, expected: Expected) -> HydrationError {
+        let current = &self.tree.current;
+        let found = (*current != self.parent && self.parent.contains(current)).then_some(current);
+
+        let path = match found {
+            Some(found) => self.path(found),
+            None => {
+                let mut path = self.path(&self.parent);
+                let mut child = self.parent.first_child();
+                let mut count = 0;
+                while let Some(node) = child {
+                    child = node.next_sibling();
+                    count += 1;
+                }
+                path.push(count);
+                path
             }
+        };
+
+        HydrationError {
+            path,
+            expected,
+            found: found.map(NativeNode::describe),
         }
+    }
 
-        let current = self.tree.current_node();
-        if !current.has_type::<web_sys::Text>() {
-            mismatch("text", &current);
+    /// Returns the child indices leading from the root to `node`.
+    fn path(&self, node: &NativeNode) -> Vec<usize> {
+        let mut path = vec![];
+        let mut node = node.clone();
+        while node != self.tree.root {
+            let mut index = 0;
+            let mut sibling = node.previous_sibling();
+            while let Some(previous) = sibling {
+                sibling = previous.previous_sibling();
+                index += 1;
+            }
+            path.push(index);
+            node = node.parent_node().unwrap();
         }
-        let text = web_sys::Text::new().unwrap();
-        current
-            .parent_node()
-            .unwrap()
-            .insert_before(&text, current.next_sibling().as_ref())
-            .unwrap();
-        self.tree.set_current_node(&text);
-        text
+        path.reverse();
+        path
     }
+}
+
+/// Walks the native nodes beneath a root in document order, like a DOM `TreeWalker`.
+struct Walker {
+    root: NativeNode,
+    current: NativeNode,
+}
 
-    fn step_marker(&mut self) {
-        let node = self.consume(Next);
-        if !is_marker(&node) {
-            mismatch("raw section marker", &node);
+impl Walker {
+    fn new(root: NativeNode) -> Self {
+        Self {
+            current: root.clone(),
+            root,
         }
     }
+
+    fn next_node(&mut self) -> Option<NativeNode> {
+        let next = match self.current.first_child() {
+            Some(child) => child,
+            None => {
+                let mut node = self.current.clone();
+                loop {
+                    if node == self.root {
+                        return None;
+                    }
+                    if let Some(next) = node.next_sibling() {
+                        break next;
+                    }
+                    node = node.parent_node()?;
+                }
+            }
+        };
+        self.current = next.clone();
+        Some(next)
+    }
+
+    fn next_sibling(&mut self) -> Option<NativeNode> {
+        if self.current == self.root {
+            return None;
+        }
+        let next = self.current.next_sibling()?;
+        self.current = next.clone();
+        Some(next)
+    }
+
+    fn previous_node(&mut self) -> Option<NativeNode> {
+        if self.current == self.root {
+            return None;
+        }
+        let previous = match self.current.previous_sibling() {
+            Some(mut node) => {
+                while let Some(last) = node.last_child() {
+                    node = last;
+                }
+                node
+            }
+            None => self.current.parent_node()?,
+        };
+        self.current = previous.clone();
+        Some(previous)
+    }
 }
 
-fn is_marker(node: &web_sys::Node) -> bool {
-    node.dyn_ref::<web_sys::Comment>()
-        .map(|comment| comment.data() == MARKER)
-        .unwrap_or(false)
+fn create_deferred(node: &Node) {
+    if let NodeKind::Portal {
+        target: Some(target),
+        ..
+    } = node.kind()
+    {
+        remove_portal_sections(target);
+    }
+
+    if let NodeKind::Suspense { .. } = node.kind() {
+        // the natives of a discarded section were created while hydrating, but are mounted once it has finished
+        if let Some(anchor) = node.to_native_anchor() {
+            let before = node.next_native_sibling();
+            for child in &*node.children_ref() {
+                child.mount(&anchor, before.as_ref());
+            }
+        }
+    }
+
+    if let NodeKind::ClientOnly(Some(deferred))
+    | NodeKind::Portal {
+        deferred: Some(deferred),
+        ..
+    }
+    | NodeKind::Head {
+        deferred: Some(deferred),
+        ..
+    } = node.kind()
+    {
+        if let Some(content) = deferred.create() {
+            node.insert(&content, None);
+        }
+    }
 }
 
-fn mismatch(expected: &str, found: &web_sys::Node) -> ! {
-    let name = js_sys::Reflect::get_prototype_of(found)
-        .unwrap()
-        .constructor()
-        .name();
-    panic!("hydrator expected {expected}, found {name}")
+/// Removes the server-rendered portal content within `parent`, which is replaced by content created in the
+/// browser.
+fn remove_portal_sections(parent: &NativeNode) {
+    remove_sections(parent, markers::PORTAL_START, markers::PORTAL_END);
+}
+
+/// Removes the marked sections within `parent`, including their markers.
+fn remove_sections(parent: &NativeNode, start: &str, end: &str) {
+    let mut depth = 0;
+    let mut child = parent.first_child();
+    while let Some(node) = child {
+        child = node.next_sibling();
+        if is_marker(&node, start) {
+            depth += 1;
+        } else if depth == 0 {
+            continue;
+        } else if is_marker(&node, end) {
+            depth -= 1;
+        }
+        parent.remove_child(&node);
+    }
+}
+
+fn is_marker(node: &NativeNode, marker: &str) -> bool {
+    node.comment_data().is_some_and(|data| data == marker)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use stardom_reactive::{effect, signal, Input, Output, Signal};
+
+    use crate::{
+        dom::virt::{self, Document},
+        events::EventOptions,
+        render::{render_to_string, Mode},
+    };
+
+    fn app() -> Node {
+        let list = Node::element("ul".into());
+        let item = Node::element("li".into());
+        item.insert(&Node::text("client".into()), None);
+        list.insert(&item, None);
+        list
+    }
+
+    #[test]
+    fn mismatches_are_reported_and_recovered() {
+        let document = Document::new();
+        document.body().set_inner_html("<ul><p>server</p></ul>");
+
+        let error = hydrate_with(document.body(), HydrateOptions::new(), app).unwrap_err();
+        assert_eq!(
+            error,
+            HydrationError {
+                path: vec![0, 0],
+                expected: Expected::Element("li".into()),
+                found: Some("<p>".into()),
+            }
+        );
+
+        // the element hydrated before the mismatch keeps no working handler
+        let clicks = Rc::new(Cell::new(0));
+        hydrate_with(document.body(), HydrateOptions::new(), || {
+            let list = app();
+            let clicks = clicks.clone();
+            list.event(&"click", EventOptions::new(), move |_| {
+                clicks.set(clicks.get() + 1)
+            })
+            .forget();
+            list
+        })
+        .unwrap_err();
+        let list = document.body().first_child().unwrap();
+        list.dispatch_event(&virt::Event::new("click"));
+        assert_eq!(clicks.get(), 0);
+
+        let (_mount, errors) =
+            hydrate_with(document.body(), HydrateOptions::new().recover(true), app).unwrap();
+        assert_eq!(errors, [error]);
+        assert_eq!(document.body().inner_html(), "<ul><li>client</li></ul>");
+    }
+
+    #[test]
+    fn adjacent_and_empty_regions_hydrate() {
+        let app = |count: Signal<String>| {
+            let text = Node::text(String::new());
+            effect({
+                let text = text.clone();
+                move || text.set_text(count.cloned())
+            });
+
+            let div = Node::element("div".into());
+            for child in [
+                Node::text("a".into()),
+                Node::fragment(),
+                text,
+                Node::text(String::new()),
+                Node::text("c".into()),
+            ] {
+                div.insert(&child, None);
+            }
+            div
+        };
+
+        let html = render_to_string(Mode::HYDRATION, || app(signal("b".to_string())));
+        assert_eq!(
+            html,
+            "<div>a<!--stardom:fragment--><!--/stardom:fragment-->b<!--stardom:text-->c</div>"
+        );
+
+        let document = Document::new();
+        document.body().set_inner_html(&html);
+        let count = Rc::new(Cell::new(None));
+        let _mount = hydrate(document.body(), {
+            let count = count.clone();
+            move || {
+                let signal = signal("b".to_string());
+                count.set(Some(signal));
+                app(signal)
+            }
+        });
+        count.get().unwrap().set("B".to_string());
+        assert_eq!(document.body().inner_html(), "<div>aBc</div>");
+    }
+
+    #[test]
+    fn text_only_elements_hydrate() {
+        let app = || {
+            let textarea = Node::element("textarea".into());
+            textarea.insert(&Node::text("a".into()), None);
+            textarea.insert(&Node::text("b".into()), None);
+            let div = Node::element("div".into());
+            div.insert(&textarea, None);
+            div.insert(&Node::text("c".into()), None);
+            div
+        };
+
+        let html = render_to_string(Mode::HYDRATION, app);
+        assert_eq!(html, "<div><textarea>ab</textarea>c</div>");
+
+        let document = Document::new();
+        document.body().set_inner_html(&html);
+        let _mount = hydrate(document.body(), app);
+        assert_eq!(
+            document.body().inner_html(),
+            "<div><textarea>ab</textarea>c</div>"
+        );
+    }
+
+    #[test]
+    fn pretty_output_hydrates_like_compact_output() {
+        let app = || {
+            let list = Node::element("ul".into());
+            for text in ["a", "b"] {
+                let item = Node::element("li".into());
+                item.insert(&Node::text(text.into()), None);
+                let fragment = Node::fragment();
+                fragment.insert(&item, None);
+                list.insert(&fragment, None);
+            }
+            let div = Node::element("div".into());
+            div.insert(&list, None);
+            div.insert(&Node::element("p".into()), None);
+            div
+        };
+
+        let compact = Document::new();
+        compact
+            .body()
+            .set_inner_html(&render_to_string(Mode::HYDRATION, app));
+        let _compact = hydrate(compact.body(), app);
+
+        let pretty = Document::new();
+        pretty
+            .body()
+            .set_inner_html(&render_to_string(Mode::HYDRATION | Mode::PRETTY, app));
+        let _pretty = hydrate(pretty.body(), app);
+
+        assert_eq!(
+            pretty.body().inner_html(),
+            "<div><ul><li>a</li><li>b</li></ul><p></p></div>"
+        );
+        assert_eq!(pretty.body().inner_html(), compact.body().inner_html());
+    }
 }
@@ -1,4 +1,5 @@
 pub mod browser;
+mod delegate;
 pub mod hydrate;
 pub mod render;
 
@@ -11,61 +12,234 @@ use std::{
 };
 
 use indexmap::IndexMap;
-use wasm_bindgen::{intern, prelude::*};
+use stardom_reactive::{Scope, Signal};
 
 use crate::{
-    component::Component,
+    attrs,
+    component::{Component, Contexts},
+    dom::{self, Listener, NativeEvent, NativeNode},
     env::{is_browser, is_hydrating},
-    events::{EventKey, EventOptions},
-    util::document,
+    events::{Event, EventKey, EventOptions},
+    head,
+    props::{self, PropValue},
 };
 
-type EventClosure = Closure<dyn FnMut(web_sys::Event)>;
+/// Comment markers delimiting sections of server-rendered HTML which the hydrator treats specially.
+pub(crate) mod markers {
+    pub const RAW: &str = "stardom:raw";
+    pub const FRAGMENT_START: &str = "stardom:fragment";
+    pub const FRAGMENT_END: &str = "/stardom:fragment";
+    pub const COMPONENT_START: &str = "stardom:component";
+    pub const COMPONENT_END: &str = "/stardom:component";
+    /// Separates adjacent text nodes, which would otherwise be parsed as a single node.
+    pub const TEXT: &str = "stardom:text";
+    pub const SERVER_START: &str = "stardom:server";
+    pub const SERVER_END: &str = "/stardom:server";
+    pub const CLIENT_START: &str = "stardom:client";
+    pub const CLIENT_END: &str = "/stardom:client";
+    pub const SUSPENSE_START: &str = "stardom:suspense";
+    pub const SUSPENSE_END: &str = "/stardom:suspense";
+    /// Follows the start of a suspense section whose content had resolved when it was rendered.
+    pub const SUSPENSE_RESOLVED: &str = "stardom:resolved";
+    pub const PORTAL_START: &str = "stardom:portal";
+    pub const PORTAL_END: &str = "/stardom:portal";
+    pub const HEAD_START: &str = "stardom:head";
+    pub const HEAD_END: &str = "/stardom:head";
+}
 
 pub(crate) enum NodeKind {
     Element {
         name: String,
         namespace: Option<String>,
         attrs: RefCell<IndexMap<String, String>>,
+        directives: Box<Directives>,
     },
     Text(RefCell<String>),
     Raw(RefCell<String>),
     Fragment,
     Component(Component),
+    /// Content only created outside of the browser.
+    ServerOnly,
+    /// Content only created in the browser.
+    ClientOnly(Option<Deferred>),
+    /// Content shown in place of a fallback once its pending async reads have resolved.
+    ///
+    /// The content is created up front, even while the fallback is shown, so that it is created in the same
+    /// order on the server and when hydrating.
+    Suspense {
+        pending: Signal<usize>,
+        content: Node,
+    },
+    /// Content mounted into `target` rather than its parent's native node.
+    ///
+    /// Outside of the browser, the content is rendered into the element whose id is `id`.
+    Portal {
+        target: Option<NativeNode>,
+        id: Option<String>,
+        mounted: Cell<bool>,
+        deferred: Option<Deferred>,
+    },
+    /// Content mounted into the document head, hiding the content of earlier head nodes with the same key.
+    ///
+    /// The content is mounted while the node is part of the main tree and not hidden. Outside of the browser, it
+    /// is rendered separately from the body.
+    Head {
+        key: Option<String>,
+        attached: Cell<bool>,
+        mounted: Cell<bool>,
+        deferred: Option<Deferred>,
+    },
+}
+
+/// The classes, styles and properties of an element, which are set individually rather than as an attribute.
+#[derive(Default)]
+pub(crate) struct Directives {
+    /// Classes added or removed by [`Node::set_class`], applied on top of the `class` attribute.
+    classes: RefCell<IndexMap<String, bool>>,
+    /// Properties set or removed by [`Node::set_style`], applied on top of the `style` attribute.
+    styles: RefCell<IndexMap<String, Option<String>>>,
+    /// Properties assigned by [`Node::set_prop`].
+    pub(crate) props: RefCell<IndexMap<String, PropValue>>,
+}
+
+/// Content whose creation is deferred until hydration has finished, rather than being hydrated.
+pub(crate) struct Deferred {
+    scope: Scope,
+    render: RefCell<Option<Box<dyn FnOnce() -> Node>>>,
+}
+
+impl Deferred {
+    pub(crate) fn new<F>(f: F) -> Self
+    where
+        F: FnOnce() -> Node + 'static,
+    {
+        let contexts = Contexts::capture();
+        Self {
+            scope: Scope::new(),
+            render: RefCell::new(Some(Box::new(move || contexts.create(f)))),
+        }
+    }
+
+    /// Creates the content, unless it was already created.
+    pub(crate) fn create(&self) -> Option<Node> {
+        let render = self.render.take()?;
+        Some(self.scope.run(render))
+    }
 }
 
 impl NodeKind {
-    fn create_native(&self) -> Option<web_sys::Node> {
-        match self {
-            Self::Element {
-                name,
-                namespace,
-                attrs,
-            } => {
-                intern(name);
-                let element = if let Some(ns) = namespace {
-                    intern(ns);
-                    document().create_element_ns(Some(ns), name)
-                } else {
-                    document().create_element(name)
+    /// Assigns the properties of an element to its native element, except those reflecting an attribute with
+    /// directives, which [`element_attrs`](Self::element_attrs) merges instead.
+    pub(crate) fn apply_props(&self, native: &NativeNode) {
+        if let Self::Element { directives, .. } = self {
+            for (key, value) in &*directives.props.borrow() {
+                if !self.merges_prop(key, value) {
+                    native.set_property(key, value);
                 }
-                .unwrap();
+            }
+        }
+    }
 
-                for (key, value) in &*attrs.borrow() {
-                    element.set_attribute(key, value).unwrap();
-                }
+    /// Returns whether the attribute `key` has directives applied on top of it.
+    fn has_directives(&self, key: &str) -> bool {
+        match self {
+            Self::Element { directives, .. } if key == "class" => {
+                !directives.classes.borrow().is_empty()
+            }
+            Self::Element { directives, .. } if key == "style" => {
+                !directives.styles.borrow().is_empty()
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns whether the property `key` reflects an attribute with directives, such as `className`, so that it
+    /// is merged with them rather than replacing their classes.
+    fn merges_prop(&self, key: &str, value: &PropValue) -> bool {
+        !matches!(value, PropValue::Js(_))
+            && props::reflected_attr(key).is_some_and(|attr| self.has_directives(attr))
+    }
+
+    /// Returns the attributes of an element, with the attributes reflected by its properties and then the classes
+    /// and styles of its directives applied.
+    pub(crate) fn element_attrs(&self) -> IndexMap<String, String> {
+        let Self::Element {
+            name,
+            attrs,
+            directives,
+            ..
+        } = self
+        else {
+            return IndexMap::new();
+        };
 
-                Some(element.unchecked_into())
+        let mut attrs = attrs.borrow().clone();
+        for (key, value) in &*directives.props.borrow() {
+            let Some(attr) = props::reflected_attr(key) else {
+                continue;
+            };
+            // the value of a textarea is rendered as its content instead
+            if matches!(value, PropValue::Js(_)) || (name == "textarea" && attr == "value") {
+                continue;
             }
-            Self::Text(value) => {
-                let text = web_sys::Text::new_with_data(&value.borrow()).unwrap();
-                Some(text.unchecked_into())
+            match value.to_attr() {
+                Some(value) => attrs.insert(attr.to_string(), value.into_owned()),
+                None => attrs.shift_remove(attr),
+            };
+        }
+        let classes = directives.classes.borrow();
+        if !classes.is_empty() {
+            let class = attrs.get("class").map_or("", String::as_str);
+            let class = classes
+                .iter()
+                .fold(class.to_string(), |class, (name, &enabled)| {
+                    attrs::toggle_class(&class, name, enabled)
+                });
+            attrs.insert("class".to_string(), class);
+        }
+        let styles = directives.styles.borrow();
+        if !styles.is_empty() {
+            let style = attrs.get("style").map_or("", String::as_str);
+            let style = styles
+                .iter()
+                .fold(style.to_string(), |style, (property, value)| {
+                    attrs::set_style_property(&style, property, value.as_deref())
+                });
+            attrs.insert("style".to_string(), style);
+        }
+        attrs
+    }
+
+    fn create_native(&self) -> Option<NativeNode> {
+        match self {
+            Self::Element {
+                name, namespace, ..
+            } => {
+                let element = dom::document().create_element(name, namespace.as_deref());
+                for (key, value) in &self.element_attrs() {
+                    element.set_attribute(key, value);
+                }
+                self.apply_props(&element);
+                Some(element)
             }
-            Self::Raw(_) | Self::Fragment | Self::Component(_) => None,
+            Self::Text(value) => Some(dom::document().create_text(&value.borrow())),
+            Self::Raw(_)
+            | Self::Fragment
+            | Self::Component(_)
+            | Self::ServerOnly
+            | Self::ClientOnly(_)
+            | Self::Suspense { .. }
+            | Self::Portal { .. }
+            | Self::Head { .. } => None,
         }
     }
 }
 
+thread_local! {
+    /// The attached head nodes with each key, the last of which has its content mounted.
+    static HEAD_KEYS: RefCell<HashMap<String, Vec<Weak<RawNode>>>> = RefCell::default();
+}
+
 #[derive(Clone)]
 pub struct Node(Rc<RawNode>);
 
@@ -73,7 +247,7 @@ struct RawNode {
     main_tree: Cell<bool>,
     kind: NodeKind,
 
-    parent: RefCell<Option<Weak<RawNode>>>,
+    parent: RefCell<Option<Weak<Self>>>,
     next: RefCell<Option<Node>>,
     children: RefCell<Vec<Node>>,
 
@@ -82,13 +256,61 @@ struct RawNode {
 
 #[derive(Default)]
 struct BrowserNode {
-    native: RefCell<Option<web_sys::Node>>,
+    native: RefCell<Option<NativeNode>>,
     events: RefCell<HashMap<u64, EventEntry>>,
 }
 
+impl BrowserNode {
+    fn has_delegated(&self) -> bool {
+        self.events
+            .borrow()
+            .values()
+            .any(|entry| entry.listener.is_none())
+    }
+}
+
+impl Drop for BrowserNode {
+    fn drop(&mut self) {
+        if self.has_delegated() {
+            if let Some(native) = &*self.native.borrow() {
+                delegate::detach(native);
+            }
+        }
+    }
+}
+
+/// An event handler added by [`Node::event`], which is removed once the handle is dropped, unless it is
+/// [forgotten](EventHandle::forget).
+#[must_use = "the handler is removed once the handle is dropped"]
+pub struct EventHandle {
+    /// The node is not kept alive by the handle, as the handler is dropped along with it.
+    node: Option<Weak<RawNode>>,
+    id: u64,
+}
+
+impl EventHandle {
+    /// Removes the handler.
+    pub fn remove(self) {}
+
+    /// Keeps the handler for as long as its node exists.
+    pub fn forget(mut self) {
+        self.node = None;
+    }
+}
+
+impl Drop for EventHandle {
+    fn drop(&mut self) {
+        if let Some(node) = self.node.take().as_ref().and_then(Node::upgrade) {
+            node.remove_event(self.id);
+        }
+    }
+}
+
 struct EventEntry {
     name: String,
-    closure: EventClosure,
+    handler: Rc<RefCell<dyn FnMut(NativeEvent)>>,
+    /// The listener added to the native element, or `None` if the event is [delegated](delegate) to the root.
+    listener: Option<Listener>,
     options: EventOptions,
 }
 
@@ -107,6 +329,13 @@ impl Node {
         } else {
             None
         };
+        if let NodeKind::Portal {
+            target: Some(target),
+            ..
+        } = &kind
+        {
+            delegate::add_root(target);
+        }
 
         let raw = RawNode {
             main_tree: Cell::new(false),
@@ -124,6 +353,7 @@ impl Node {
             name,
             namespace: None,
             attrs: RefCell::default(),
+            directives: Box::default(),
         };
         Self::create(kind)
     }
@@ -133,6 +363,7 @@ impl Node {
             name,
             namespace: Some(namespace),
             attrs: RefCell::default(),
+            directives: Box::default(),
         };
         Self::create(kind)
     }
@@ -201,7 +432,14 @@ impl Node {
         }
 
         if let Some(native) = self.to_native_anchor() {
-            let before = before.and_then(Self::to_native_sibling);
+            let before = match before {
+                Some(before) => before.to_native_sibling(),
+                // appending to a non-native container places the child before whatever follows the container
+                None if self.native_node_ref().is_none() && !self.is_portal() => {
+                    self.next_native_sibling()
+                }
+                None => None,
+            };
             child.mount(&native, before.as_ref());
         }
     }
@@ -269,7 +507,9 @@ impl Node {
 
     pub fn set_text(&self, value: String) {
         if let NodeKind::Text(content) = &self.0.kind {
-            self.native_ref().unwrap().set_text_content(Some(&value));
+            if let Some(native) = self.native_node_ref() {
+                native.set_text_content(&value);
+            }
             *content.borrow_mut() = value;
         } else {
             self.expect_kind(Expect::Text);
@@ -285,12 +525,7 @@ impl Node {
             }
 
             if is_browser() {
-                let range = web_sys::Range::new().unwrap();
-                let doc = range.create_contextual_fragment(&value).unwrap();
-                let list = doc.child_nodes();
-
-                for i in 0..list.length() {
-                    let native = list.item(i).unwrap();
+                for native in dom::document().parse_fragment(&value) {
                     let holder = Self::fragment();
                     holder.manual_bind(native);
                     self.insert(&holder, None);
@@ -315,12 +550,13 @@ impl Node {
 
     pub fn set_attr(&self, key: String, value: String) -> Option<String> {
         if let NodeKind::Element { attrs, .. } = &self.0.kind {
-            if let Some(native) = self.native_ref() {
-                intern(&key);
-                native
-                    .unchecked_ref::<web_sys::Element>()
-                    .set_attribute(&key, &value)
-                    .unwrap();
+            if let Some(native) = self.native_node_ref() {
+                if self.0.kind.has_directives(&key) {
+                    let prev = attrs.borrow_mut().insert(key.clone(), value);
+                    native.set_attribute(&key, &self.0.kind.element_attrs()[&key]);
+                    return prev;
+                }
+                native.set_attribute(&key, &value);
             }
 
             attrs.borrow_mut().insert(key, value)
@@ -331,11 +567,13 @@ impl Node {
 
     pub fn remove_attr(&self, key: &str) -> Option<String> {
         if let NodeKind::Element { attrs, .. } = &self.0.kind {
-            if let Some(native) = self.native_ref() {
-                native
-                    .unchecked_ref::<web_sys::Element>()
-                    .remove_attribute(key)
-                    .unwrap();
+            if let Some(native) = self.native_node_ref() {
+                if self.0.kind.has_directives(key) {
+                    let prev = attrs.borrow_mut().shift_remove(key);
+                    native.set_attribute(key, &self.0.kind.element_attrs()[key]);
+                    return prev;
+                }
+                native.remove_attribute(key);
             }
 
             attrs.borrow_mut().shift_remove(key)
@@ -344,50 +582,182 @@ impl Node {
         }
     }
 
-    pub fn event<K, F>(&self, key: &K, options: EventOptions, mut f: F)
+    /// Adds the class `name` if `enabled`, or removes it otherwise, regardless of the `class` attribute.
+    ///
+    /// The attribute keeps the value it was set to, and is rendered with the classes of this method applied.
+    pub fn set_class(&self, name: &str, enabled: bool) {
+        if let NodeKind::Element { directives, .. } = &self.0.kind {
+            if let Some(native) = self.native_node_ref() {
+                native.toggle_class(name, enabled);
+            }
+
+            directives
+                .classes
+                .borrow_mut()
+                .insert(name.to_string(), enabled);
+        } else {
+            self.expect_kind(Expect::Element);
+        }
+    }
+
+    /// Sets the style `property` to `value`, or removes it if `value` is `None`, regardless of the `style`
+    /// attribute.
+    ///
+    /// The attribute keeps the value it was set to, and is rendered with the properties of this method applied.
+    pub fn set_style(&self, property: &str, value: Option<String>) {
+        if let NodeKind::Element { directives, .. } = &self.0.kind {
+            if let Some(native) = self.native_node_ref() {
+                native.set_style_property(property, value.as_deref());
+            }
+
+            directives
+                .styles
+                .borrow_mut()
+                .insert(property.to_string(), value);
+        } else {
+            self.expect_kind(Expect::Element);
+        }
+    }
+
+    /// Assigns `value` to the DOM property `key`, which unlike an attribute may reflect the current state of the
+    /// element, such as the `value` of an input after the user has edited it.
+    ///
+    /// Outside of the browser, properties reflecting an attribute are rendered as that attribute.
+    pub fn set_prop<V>(&self, key: &str, value: V)
+    where
+        V: Into<PropValue>,
+    {
+        if let NodeKind::Element { directives, .. } = &self.0.kind {
+            let value = value.into();
+            if let Some(native) = self.native_node_ref() {
+                if self.0.kind.merges_prop(key, &value) {
+                    let attr = props::reflected_attr(key).unwrap();
+                    directives.props.borrow_mut().insert(key.to_string(), value);
+                    native.set_attribute(attr, &self.0.kind.element_attrs()[attr]);
+                    return;
+                }
+                native.set_property(key, &value);
+            }
+
+            directives.props.borrow_mut().insert(key.to_string(), value);
+        } else {
+            self.expect_kind(Expect::Element);
+        }
+    }
+
+    /// Adds a handler for the event `key`, which is removed once the returned handle is dropped.
+    ///
+    /// Outside of the browser, where no events are dispatched, the handler is dropped right away.
+    pub fn event<K, F>(&self, key: &K, options: EventOptions, mut f: F) -> EventHandle
     where
         K: EventKey,
-        F: FnMut(K::Event) + 'static,
+        F: FnMut(Event<K::Event>) + 'static,
     {
         if !matches!(self.0.kind, NodeKind::Element { .. }) {
             self.expect_kind(Expect::Element);
         }
+        if self.0.browser.is_none() {
+            return EventHandle { node: None, id: 0 };
+        }
 
         thread_local!(static ID: Cell<u64> = const { Cell::new(0) });
         let id = ID.replace(ID.get() + 1);
 
         let name = key.name();
-        let opts = options.to_native(name);
 
-        let node = self.clone();
-        let closure = EventClosure::new(move |ev: web_sys::Event| {
-            f(ev.dyn_into().expect("event type mismatch"));
+        // the handler is owned by the node, so it must not keep the node alive
+        let node = self.downgrade();
+        let handler = Rc::new(RefCell::new(move |ev: NativeEvent| {
+            let Some(node) = Self::upgrade(&node) else {
+                return;
+            };
+            if options.self_only && ev.target() != node.native_node() {
+                return;
+            }
+            if options
+                .key
+                .is_some_and(|key| ev.key().as_deref() != Some(key))
+            {
+                return;
+            }
+            if options.prevent_default {
+                ev.prevent_default();
+            }
+            if options.stop_propagation {
+                ev.stop_propagation();
+            }
+
+            f(Event::new(ev, node.native_node()));
 
             if options.once {
-                node.browser().events.borrow_mut().remove(&id);
+                node.remove_event(id);
             }
+        }));
+        let listener = (!delegate::is_delegated(name)).then(|| {
+            let handler = handler.clone();
+            Listener::new(move |ev| (handler.borrow_mut())(ev))
         });
+        let entry = EventEntry {
+            name: name.to_string(),
+            handler,
+            listener,
+            options,
+        };
 
         if is_browser() {
-            self.native_ref()
-                .unwrap()
-                .unchecked_ref::<web_sys::Element>()
-                .add_event_listener_with_callback_and_add_event_listener_options(
-                    intern(name),
-                    closure.as_ref().unchecked_ref(),
-                    &opts,
-                )
-                .unwrap();
-        }
-
-        self.browser().events.borrow_mut().insert(
+            self.attach_event(&self.native_node_ref().unwrap(), &entry);
+        }
+
+        self.browser().events.borrow_mut().insert(id, entry);
+        EventHandle {
+            node: Some(self.downgrade()),
             id,
-            EventEntry {
-                name: name.to_string(),
-                closure,
-                options,
-            },
-        );
+        }
+    }
+
+    fn remove_event(&self, id: u64) {
+        let Some(entry) = self.browser().events.borrow_mut().remove(&id) else {
+            return;
+        };
+        let Some(native) = self.native_node_ref() else {
+            return;
+        };
+        match &entry.listener {
+            Some(listener) => native.remove_event_listener(&entry.name, listener, entry.options),
+            None if !self.browser().has_delegated() => delegate::detach(&native),
+            None => {}
+        }
+    }
+
+    /// Adds the listeners of the node's event handlers to `native`.
+    fn attach_events(&self, native: &NativeNode) {
+        for entry in self.browser().events.borrow().values() {
+            self.attach_event(native, entry);
+        }
+    }
+
+    fn attach_event(&self, native: &NativeNode, entry: &EventEntry) {
+        match &entry.listener {
+            Some(listener) => native.add_event_listener(&entry.name, listener, entry.options),
+            None => delegate::attach(self, native, &entry.name, entry.options.capture),
+        }
+    }
+
+    /// Invokes the handlers of the node for an event delegated to the root.
+    fn invoke_delegated(&self, name: &str, capture: bool, ev: &NativeEvent) {
+        let handlers = self
+            .browser()
+            .events
+            .borrow()
+            .values()
+            .filter(|entry| {
+                entry.listener.is_none() && entry.name == name && entry.options.capture == capture
+            })
+            .map(|entry| entry.handler.clone())
+            .collect::<Vec<_>>();
+        for handler in handlers {
+            (handler.borrow_mut())(ev.clone());
+        }
     }
 
     // Other Utilities //
@@ -416,15 +786,28 @@ impl Node {
         self.0.children.borrow().clone()
     }
 
-    pub fn children_ref(&self) -> Ref<Vec<Self>> {
+    pub fn children_ref(&self) -> Ref<'_, Vec<Self>> {
         self.0.children.borrow()
     }
 
+    /// Returns the browser's DOM node, or `None` if the node has no native node or belongs to a
+    /// [virtual](crate::dom::virt) DOM. See [`native_node`](Self::native_node) for either.
     pub fn native(&self) -> Option<web_sys::Node> {
         self.native_ref().map(|native| native.clone())
     }
 
-    pub fn native_ref(&self) -> Option<Ref<web_sys::Node>> {
+    /// Like [`native`](Self::native), but borrows the node.
+    pub fn native_ref(&self) -> Option<Ref<'_, web_sys::Node>> {
+        self.native_node_ref()
+            .and_then(|native| Ref::filter_map(native, NativeNode::as_web).ok())
+    }
+
+    /// Returns the native node of either DOM.
+    pub fn native_node(&self) -> Option<NativeNode> {
+        self.native_node_ref().map(|native| native.clone())
+    }
+
+    pub fn native_node_ref(&self) -> Option<Ref<'_, NativeNode>> {
         self.0
             .browser
             .as_ref()
@@ -433,7 +816,7 @@ impl Node {
 
     // Internal //
 
-    fn kind(&self) -> &NodeKind {
+    pub(crate) fn kind(&self) -> &NodeKind {
         &self.0.kind
     }
 
@@ -444,7 +827,7 @@ impl Node {
             .expect("not running within a browser environment")
     }
 
-    fn manual_bind<N: Into<web_sys::Node>>(&self, node: N) {
+    fn manual_bind<N: Into<NativeNode>>(&self, node: N) {
         self.browser().native.replace(Some(node.into()));
     }
 
@@ -463,13 +846,106 @@ impl Node {
             NodeKind::Component(component) if value => {
                 component.on_mount();
             }
+            NodeKind::Head { .. } if is_browser() => {
+                if value {
+                    self.attach_head();
+                } else {
+                    self.detach_head();
+                }
+            }
             _ => {}
         }
     }
 
-    fn mount(&self, parent: &web_sys::Node, before: Option<&web_sys::Node>) {
-        if let Some(native) = self.native_ref() {
-            parent.insert_before(&native, before).unwrap();
+    /// Mounts a head node's content into the document head, hiding the content of the head node with the same
+    /// key mounted before it.
+    fn attach_head(&self) {
+        let NodeKind::Head { key, attached, .. } = &self.0.kind else {
+            return;
+        };
+        if attached.replace(true) {
+            return;
+        }
+
+        let head = dom::document().head();
+        delegate::add_root(&head);
+        if let Some(key) = key {
+            let previous = HEAD_KEYS.with_borrow_mut(|keys| {
+                let stack = keys.entry(key.clone()).or_default();
+                let previous = stack.iter().rev().find_map(Self::upgrade);
+                stack.push(self.downgrade());
+                previous
+            });
+            match previous {
+                Some(previous) => previous.set_head_mounted(&head, false),
+                None => head::remove_existing(&head, key),
+            }
+        }
+        self.set_head_mounted(&head, true);
+    }
+
+    /// Unmounts a head node's content, showing the content of the head node with the same key mounted before it
+    /// again.
+    fn detach_head(&self) {
+        let NodeKind::Head { key, attached, .. } = &self.0.kind else {
+            return;
+        };
+        if !attached.replace(false) {
+            return;
+        }
+
+        let head = dom::document().head();
+        self.set_head_mounted(&head, false);
+        if let Some(key) = key {
+            let this = self.downgrade();
+            let previous = HEAD_KEYS.with_borrow_mut(|keys| {
+                let stack = keys.get_mut(key)?;
+                let top = stack.last().is_some_and(|last| last.ptr_eq(&this));
+                stack.retain(|node| !node.ptr_eq(&this) && node.strong_count() > 0);
+                let previous = stack.last().and_then(Self::upgrade).filter(|_| top);
+                if stack.is_empty() {
+                    keys.remove(key);
+                }
+                previous
+            });
+            if let Some(previous) = previous {
+                previous.set_head_mounted(&head, true);
+            }
+        }
+    }
+
+    fn set_head_mounted(&self, head: &NativeNode, value: bool) {
+        let NodeKind::Head { mounted, .. } = &self.0.kind else {
+            return;
+        };
+        if mounted.replace(value) == value {
+            return;
+        }
+        for child in &*self.0.children.borrow() {
+            if value {
+                child.mount(head, None);
+            } else {
+                child.unmount(head);
+            }
+        }
+    }
+
+    fn mount(&self, parent: &NativeNode, before: Option<&NativeNode>) {
+        if let NodeKind::Portal {
+            target: Some(target),
+            mounted,
+            ..
+        } = &self.0.kind
+        {
+            // portals mount their content into their target when they themselves are mounted
+            mounted.set(true);
+            for child in &*self.0.children.borrow() {
+                child.mount(target, None);
+            }
+        } else if let NodeKind::Head { .. } = &self.0.kind {
+            // head content is mounted into the head while part of the main tree instead
+        } else if let Some(native) = self.native_node_ref() {
+            parent.insert_before(&native, before);
         } else {
             for child in &*self.0.children.borrow() {
                 child.mount(parent, before);
@@ -477,9 +953,20 @@ impl Node {
         }
     }
 
-    fn unmount(&self, parent: &web_sys::Node) {
-        if let Some(native) = self.native_ref() {
-            parent.remove_child(&native).unwrap();
+    fn unmount(&self, parent: &NativeNode) {
+        if let NodeKind::Portal {
+            target: Some(target),
+            mounted,
+            ..
+        } = &self.0.kind
+        {
+            mounted.set(false);
+            for child in &*self.0.children.borrow() {
+                child.unmount(target);
+            }
+        } else if let NodeKind::Head { .. } = &self.0.kind {
+        } else if let Some(native) = self.native_node_ref() {
+            parent.remove_child(&native);
         } else {
             for child in &*self.0.children.borrow() {
                 child.unmount(parent);
@@ -487,11 +974,34 @@ impl Node {
         }
     }
 
+    /// Returns the context of type `T` registered by the nearest component enclosing `self` in the node tree.
+    ///
+    /// Unlike [`try_context`](crate::component::try_context), this works after the components have been
+    /// created, such as within an effect.
+    pub(crate) fn tree_context<T: Clone + 'static>(&self) -> Option<T> {
+        match &self.0.kind {
+            NodeKind::Component(component) => component.context(),
+            _ => None,
+        }
+        .or_else(|| self.parent()?.tree_context())
+    }
+
     /// Finds the nearest ancestral native node, **including** `self`.
     ///
-    /// [`Node::insert`] uses the returned node as the mount point.
-    fn to_native_anchor(&self) -> Option<web_sys::Node> {
-        self.native()
+    /// [`Node::insert`] uses the returned node as the mount point. A portal's content is anchored to its target
+    /// for as long as the portal is mounted.
+    fn to_native_anchor(&self) -> Option<NativeNode> {
+        if let NodeKind::Portal {
+            target, mounted, ..
+        } = &self.0.kind
+        {
+            return target.clone().filter(|_| mounted.get());
+        }
+        if let NodeKind::Head { mounted, .. } = &self.0.kind {
+            return mounted.get().then(|| dom::document().head());
+        }
+
+        self.native_node()
             .or_else(|| self.parent().as_ref().and_then(Self::to_native_anchor))
     }
 
@@ -500,7 +1010,7 @@ impl Node {
     /// The node returned is the next native sibling relative to `self`'s conceptual position within the native DOM tree.
     ///
     /// Consider the following node tree:
-    /// ```
+    /// ```ignore
     /// div! {
     ///     fragment! {
     ///         fragment!(); // A
@@ -511,12 +1021,15 @@ impl Node {
     /// ```
     /// Calling this method on `A` would return `B`, since it's the next native node under its native parent.
     /// If `B` were not there, it would return `None`, as there would be no subsequent native nodes.
-    fn next_native_sibling(&self) -> Option<web_sys::Node> {
+    fn next_native_sibling(&self) -> Option<NativeNode> {
         if let Some(next) = &*self.0.next.borrow() {
-            next.native().or_else(|| next.next_native_sibling())
+            next.native_node().or_else(|| next.next_native_sibling())
         } else {
             self.parent().and_then(|parent| {
-                if matches!(parent.0.kind, NodeKind::Element { .. }) {
+                if matches!(
+                    parent.0.kind,
+                    NodeKind::Element { .. } | NodeKind::Portal { .. } | NodeKind::Head { .. }
+                ) {
                     None
                 } else {
                     parent.next_native_sibling()
@@ -528,8 +1041,12 @@ impl Node {
     /// Finds the nearest native sibling node, **including** `self`.
     ///
     /// See [Self::next_native_sibling] for an explanation.
-    fn to_native_sibling(&self) -> Option<web_sys::Node> {
-        self.native().or_else(|| self.next_native_sibling())
+    fn to_native_sibling(&self) -> Option<NativeNode> {
+        self.native_node().or_else(|| self.next_native_sibling())
+    }
+
+    fn is_portal(&self) -> bool {
+        matches!(self.0.kind, NodeKind::Portal { .. } | NodeKind::Head { .. })
     }
 
     fn downgrade(&self) -> Weak<RawNode> {
@@ -553,6 +1070,11 @@ impl Node {
             NodeKind::Raw(_) => "raw",
             NodeKind::Fragment => "fragment",
             NodeKind::Component(_) => "component",
+            NodeKind::ServerOnly => "server-only",
+            NodeKind::ClientOnly(_) => "client-only",
+            NodeKind::Suspense { .. } => "suspense",
+            NodeKind::Portal { .. } => "portal",
+            NodeKind::Head { .. } => "head",
         };
 
         panic!("expected {}, found {}", expected, found);
@@ -631,3 +1153,54 @@ enum Expect {
     Text,
     Raw,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::{
+        browser::mount,
+        dom::virt::{Document, Event},
+    };
+
+    #[test]
+    fn dropped_event_handles_remove_handlers() {
+        let document = Document::new();
+        let count = Rc::new(Cell::new(0));
+        let handle = Rc::new(RefCell::new(None));
+        let _mount = mount(document.body(), || {
+            let button = Node::element("button".into());
+            let count = count.clone();
+            *handle.borrow_mut() = Some(button.event(&"click", EventOptions::new(), move |_| {
+                count.set(count.get() + 1)
+            }));
+            button
+        });
+
+        let button = document.body().first_child().unwrap();
+        button.dispatch_event(&Event::new("click"));
+        handle.take().unwrap().remove();
+        button.dispatch_event(&Event::new("click"));
+        assert_eq!(count.get(), 1);
+    }
+
+    #[test]
+    fn event_handles_do_not_keep_nodes_alive() {
+        let document = Document::new();
+        let token = Rc::new(());
+        let _mount = mount(document.body(), || {
+            let button = Node::element("button".into());
+            let captured = token.clone();
+            let handle = button.event(&"click", EventOptions::new(), move |_| {
+                let _ = &captured;
+            });
+
+            // the handler is dropped along with its node, while the handle is still held
+            drop(button);
+            assert_eq!(Rc::strong_count(&token), 1);
+            drop(handle);
+            Node::fragment()
+        });
+    }
+}
@@ -1,22 +1,129 @@
-use std::fmt::{self, Write};
+use std::{
+    borrow::Cow,
+    fmt::{self, Write},
+    io, mem,
+    pin::Pin,
+    task::{Context, Poll},
+};
 
 use bitflags::bitflags;
+use bytes::Bytes;
+use futures::{AsyncWrite, AsyncWriteExt, Stream, StreamExt};
+use stardom_reactive::{Input, Signal};
 
 use crate::{
-    env::{self, Env},
-    node::{Node, NodeKind},
+    node::{markers, Node, NodeKind},
+    state,
+    suspense::ServerRender,
 };
 
-const VOID: &[&str] = &[
-    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
-    "wbr",
+stardom_macros::create_named!(void);
+
+/// Elements whose text is serialized as is, without escaping.
+pub(crate) const RAW_TEXT: &[&str] = &[
+    "script",
+    "style",
+    "xmp",
+    "iframe",
+    "noembed",
+    "noframes",
+    "plaintext",
+];
+
+/// Elements which only contain text, which is escaped.
+pub(crate) const ESCAPABLE_RAW_TEXT: &[&str] = &["textarea", "title"];
+
+pub(crate) const HTML_NAMESPACE: &str = "http://www.w3.org/1999/xhtml";
+pub(crate) const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+pub(crate) const MATHML_NAMESPACE: &str = "http://www.w3.org/1998/Math/MathML";
+const XLINK_NAMESPACE: &str = "http://www.w3.org/1999/xlink";
+
+/// Elements whose box is block-level, so whitespace around them does not affect the layout of their siblings.
+///
+/// See <https://developer.mozilla.org/en-US/docs/Glossary/Block-level_content>.
+const BLOCK: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "dd",
+    "details",
+    "dialog",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hgroup",
+    "hr",
+    "html",
+    "li",
+    "main",
+    "menu",
+    "nav",
+    "ol",
+    "p",
+    "section",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "ul",
 ];
 
+/// Returns how the children of an element are serialized, or `None` if it is void.
+fn element_text(name: &str, namespace: Option<&str>) -> Option<Text> {
+    if namespace.is_some_and(|ns| ns != HTML_NAMESPACE) {
+        Some(Text::Normal)
+    } else if RAW_TEXT.contains(&name) {
+        Some(Text::Raw)
+    } else if ESCAPABLE_RAW_TEXT.contains(&name) {
+        Some(Text::Escapable)
+    } else if VOID.contains(&name) {
+        None
+    } else {
+        Some(Text::Normal)
+    }
+}
+
+/// Returns whether an element only contains text, so its children are rendered without hydration markers.
+pub(crate) fn is_text_only(name: &str, namespace: Option<&str>) -> bool {
+    matches!(
+        element_text(name, namespace),
+        Some(Text::Raw | Text::Escapable)
+    )
+}
+
 bitflags! {
     #[derive(Clone, Copy, PartialEq, Eq, Debug)]
     pub struct Mode: u8 {
+        /// Places block-level elements on their own indented lines.
+        ///
+        /// Whitespace is only inserted between the children of block-level elements which contain nothing but
+        /// other block-level elements, so inline and preformatted content is rendered as is, and the output
+        /// hydrates the same as without this flag.
         const PRETTY = 0b01;
         const HYDRATION = 0b10;
+        /// Renders well-formed XML instead of HTML.
+        ///
+        /// Elements created without a namespace are placed in the namespace the HTML parser would have given
+        /// them, and namespaces are declared wherever they change. Elements without content are self-closing.
+        const XML = 0b100;
     }
 }
 
@@ -35,113 +142,1086 @@ where
     out
 }
 
+/// Renders a standalone XML document, such as an SVG image, with [`Mode::XML`] added to `mode`.
+///
+/// ```ignore
+/// let svg = render_xml_document(Mode::empty(), || chart(data));
+/// ```
+pub fn render_xml_document<F>(mode: Mode, f: F) -> String
+where
+    F: FnOnce() -> Node,
+{
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    render(&mut out, mode | Mode::XML, f).unwrap();
+    out
+}
+
 pub fn render<W, F>(w: &mut W, mode: Mode, f: F) -> fmt::Result
 where
     W: Write,
     F: FnOnce() -> Node,
 {
-    env::with(Env::Render, || {
-        stardom_reactive::run(|dispose| {
-            let res = render_node(w, mode, &f());
-            dispose();
-            res
-        })
-    })
+    let mut cx = Cx::new(mode);
+    ServerRender::new().enter(|| render_root(w, &mut cx, &f()))
+}
+
+/// The output of [`render_page`], split into the content of the document's head and body.
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct Page {
+    /// The elements placed in the head by [`head`](crate::head) nodes, in the order they were created.
+    pub head: String,
+    pub body: String,
+    /// The script containing the [transferred](crate::state) state in hydration mode, which is part of the body
+    /// when rendered by other render functions.
+    pub state: String,
+}
+
+/// Like [`render_to_string`], but also renders the elements placed in the head by [`head`](crate::head) nodes,
+/// which other render functions discard.
+pub fn render_page<F>(mode: Mode, f: F) -> Page
+where
+    F: FnOnce() -> Node,
+{
+    let mut page = Page::default();
+    let mut cx = Cx::new(mode);
+    ServerRender::new()
+        .enter(|| render_page_parts(&mut page, &mut cx, &f()))
+        .unwrap();
+    page
+}
+
+/// Like [`render_page`], but waits for all pending async reads beneath [suspense](crate::suspense) nodes to
+/// resolve before rendering.
+pub async fn render_page_async<F>(mode: Mode, f: F) -> Page
+where
+    F: FnOnce() -> Node,
+{
+    let mut server = ServerRender::new();
+    let node = server.enter(f);
+    server.resolve().await;
+
+    let mut page = Page::default();
+    let mut cx = Cx::new(mode);
+    server
+        .enter(|| render_page_parts(&mut page, &mut cx, &node))
+        .unwrap();
+    page
 }
 
-fn render_node<W: Write>(w: &mut W, mode: Mode, node: &Node) -> fmt::Result {
-    let nl = |w: &mut W| {
-        if mode.contains(Mode::PRETTY) {
-            writeln!(w)
+/// The markup surrounding the content of a [`Page`] in [`render_document`].
+#[derive(Clone, Default, Debug)]
+pub enum Shell {
+    /// A minimal HTML5 document, with the body rendered directly into `<body>`.
+    #[default]
+    Default,
+    /// An HTML document with a `<!--stardom:body-->` placeholder, which is replaced by the body.
+    ///
+    /// The head content is inserted before `</head>`, and the scripts before `</body>`.
+    Template(String),
+}
+
+impl Shell {
+    /// The placeholder which the body replaces within a [`Shell::Template`].
+    pub const BODY: &'static str = "<!--stardom:body-->";
+}
+
+/// The paths of the JS and wasm files generated by `stardom build`, as served to the browser.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Assets {
+    pub js: String,
+    pub wasm: String,
+}
+
+impl Assets {
+    /// Returns the assets of the binary named `bin`, served from the directory at `base`.
+    ///
+    /// `stardom build` outputs `{bin}.js` and `{bin}_bg.wasm` into its `out-dir`, which is `dist` by default.
+    ///
+    /// ```
+    /// # use stardom_core::render::Assets;
+    /// let assets = Assets::new("/dist", "app");
+    /// assert_eq!(assets.js, "/dist/app.js");
+    /// assert_eq!(assets.wasm, "/dist/app_bg.wasm");
+    /// ```
+    pub fn new(base: &str, bin: &str) -> Self {
+        let base = base.trim_end_matches('/');
+        Self {
+            js: format!("{base}/{bin}.js"),
+            wasm: format!("{base}/{bin}_bg.wasm"),
+        }
+    }
+}
+
+/// Renders a complete HTML document from a rendered [`Page`].
+///
+/// If `assets` are given, the wasm module is preloaded from the head and initialized by a script at the end of
+/// the body, after the transferred state.
+///
+/// ```ignore
+/// let page = render_page(Mode::HYDRATION, app);
+/// let html = render_document(&Shell::Default, &page, Some(&Assets::new("/dist", "app")));
+/// ```
+///
+/// # Panics
+///
+/// Panics if a [`Shell::Template`] is missing its body placeholder, `</head>` or `</body>`, or if they are not
+/// in that order.
+pub fn render_document(shell: &Shell, page: &Page, assets: Option<&Assets>) -> String {
+    let mut head = page.head.clone();
+    let mut scripts = page.state.clone();
+    if let Some(Assets { js, wasm }) = assets {
+        write!(
+            head,
+            "<link rel=\"modulepreload\" href=\"{}\">\
+             <link rel=\"preload\" href=\"{}\" as=\"fetch\" type=\"application/wasm\" crossorigin>",
+            escape(js, true),
+            escape(wasm, true),
+        )
+        .unwrap();
+
+        // string literals, with `<` escaped so that the script cannot be closed early
+        let literal = |s: &str| serde_json::to_string(s).unwrap().replace('<', "\\u003c");
+        write!(
+            scripts,
+            "<script type=\"module\">import init from {};init({});</script>",
+            literal(js),
+            literal(wasm),
+        )
+        .unwrap();
+    }
+
+    match shell {
+        Shell::Default => format!(
+            "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+             <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+             {head}</head><body>{}{scripts}</body></html>",
+            page.body,
+        ),
+        Shell::Template(template) => {
+            let find = |tag: &str| {
+                template
+                    .find(tag)
+                    .unwrap_or_else(|| panic!("document template is missing `{tag}`"))
+            };
+            let (head_end, placeholder, body_end) =
+                (find("</head>"), find(Shell::BODY), find("</body>"));
+            assert!(
+                head_end <= placeholder && placeholder + Shell::BODY.len() <= body_end,
+                "document template must have `</head>`, `{}` and `</body>` in that order",
+                Shell::BODY,
+            );
+
+            let mut out = String::with_capacity(template.len() + head.len() + page.body.len());
+            out.push_str(&template[..head_end]);
+            out.push_str(&head);
+            out.push_str(&template[head_end..placeholder]);
+            out.push_str(&page.body);
+            out.push_str(&template[placeholder + Shell::BODY.len()..body_end]);
+            out.push_str(&scripts);
+            out.push_str(&template[body_end..]);
+            out
+        }
+    }
+}
+
+pub async fn render_to_string_async<F>(mode: Mode, f: F) -> String
+where
+    F: FnOnce() -> Node,
+{
+    let mut out = String::new();
+    render_async(&mut out, mode, f).await.unwrap();
+    out
+}
+
+/// Like [`render`], but waits for all pending async reads beneath [suspense](crate::suspense) nodes to resolve
+/// before rendering.
+pub async fn render_async<W, F>(w: &mut W, mode: Mode, f: F) -> fmt::Result
+where
+    W: Write,
+    F: FnOnce() -> Node,
+{
+    let mut server = ServerRender::new();
+    let node = server.enter(f);
+    server.resolve().await;
+    let mut cx = Cx::new(mode);
+    server.enter(move || render_root(w, &mut cx, &node))
+}
+
+/// Renders the tree as a stream of HTML chunks.
+///
+/// The first chunk contains the whole tree, with the fallbacks of suspended [suspense](crate::suspense) nodes
+/// rendered in place of their content. Each suspense node is then streamed as its own chunk once its async
+/// reads resolve, in whichever order they resolve, along with an inline script which swaps it into place.
+pub fn render_stream<F>(mode: Mode, f: F) -> RenderStream
+where
+    F: FnOnce() -> Node + 'static,
+{
+    RenderStream {
+        server: ServerRender::new(),
+        cx: Cx::streaming(mode),
+        render: Some(Box::new(f)),
+        root: None,
+    }
+}
+
+/// Streams the tree into `w`, flushing after each chunk.
+///
+/// See [`render_stream`] for details.
+pub async fn render_to_async_write<W, F>(w: &mut W, mode: Mode, f: F) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+    F: FnOnce() -> Node + 'static,
+{
+    let mut stream = render_stream(mode, f);
+    while let Some(chunk) = stream.next().await {
+        w.write_all(&chunk).await?;
+        w.flush().await?;
+    }
+    Ok(())
+}
+
+/// A stream of HTML chunks, created by [`render_stream`].
+pub struct RenderStream {
+    server: ServerRender,
+    cx: Cx,
+    render: Option<Box<dyn FnOnce() -> Node>>,
+    root: Option<Node>,
+}
+
+impl Stream for RenderStream {
+    type Item = Bytes;
+
+    fn poll_next(self: Pin<&mut Self>, task: &mut Context) -> Poll<Option<Bytes>> {
+        let this = self.get_mut();
+        let cx = &mut this.cx;
+
+        if let Some(render) = this.render.take() {
+            let mut shell = String::new();
+            let root = this.server.enter(|| {
+                let root = render();
+                render_root(&mut shell, cx, &root).unwrap();
+                root
+            });
+            this.root = Some(root);
+            return Poll::Ready(Some(shell.into()));
+        }
+
+        if cx.suspended.is_empty() {
+            // drop the tree while its runtime is active
+            let root = this.root.take();
+            this.server.enter(move || drop(root));
+            return Poll::Ready(None);
+        }
+
+        // once every task has finished, boundaries still suspended will never resolve, so they are streamed as is
+        let finished = this.server.poll(task).is_ready();
+        let chunk = this.server.enter(|| cx.flush_suspended(finished));
+        if chunk.is_empty() {
+            Poll::Pending
         } else {
-            Ok(())
+            Poll::Ready(Some(chunk.into()))
         }
-    };
+    }
+}
+
+/// State shared across a single render.
+struct Cx {
+    mode: Mode,
+    /// Whether suspended boundaries are streamed later, rather than rendered with their fallback.
+    streaming: bool,
+    suspended: Vec<Suspended>,
+    next_id: usize,
+    swap_script: bool,
+    /// Portals yet to be rendered, along with the id of their target element.
+    portals: Vec<(String, Node)>,
+    /// Head nodes, rendered separately from the body.
+    heads: Vec<Node>,
+    /// Whether the content being rendered is not hydrated, so its fragments and text need no markers.
+    opaque: bool,
+    /// How the text being rendered is serialized.
+    text: Text,
+    /// Whether the nodes being rendered are placed on their own lines.
+    layout: bool,
+    /// The indentation of the lines.
+    depth: usize,
+    /// Whether no line has been written yet, which therefore needs no preceding newline.
+    fresh: bool,
+    /// The namespace of the element being rendered, which its children without a namespace are placed in.
+    namespace: Option<String>,
+    /// The default namespace declared by the element being rendered.
+    xmlns: Option<String>,
+}
+
+/// How text is serialized, depending on the element containing it.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Text {
+    Normal,
+    /// Within a [raw text](RAW_TEXT) element.
+    Raw,
+    /// Within an [escapable raw text](ESCAPABLE_RAW_TEXT) element.
+    Escapable,
+}
+
+/// A suspense node whose fallback was streamed, to be replaced once its reads resolve.
+struct Suspended {
+    id: usize,
+    node: Node,
+    pending: Signal<usize>,
+    /// Whether the node is within content which is not hydrated.
+    opaque: bool,
+}
+
+impl Cx {
+    fn new(mode: Mode) -> Self {
+        Self {
+            mode,
+            streaming: false,
+            suspended: vec![],
+            next_id: 0,
+            swap_script: false,
+            portals: vec![],
+            heads: vec![],
+            opaque: false,
+            text: Text::Normal,
+            layout: false,
+            depth: 0,
+            fresh: false,
+            namespace: None,
+            xmlns: None,
+        }
+    }
+
+    /// Returns whether fragments, components and adjacent text are delimited by comment markers.
+    fn markers(&self) -> bool {
+        self.mode.contains(Mode::HYDRATION) && !self.opaque
+    }
+
+    /// Returns whether the children of `node` are placed on their own lines.
+    fn is_layout(&self, node: &Node) -> bool {
+        let children = node.children_ref();
+        self.mode.contains(Mode::PRETTY)
+            && !children.is_empty()
+            && children.iter().all(is_block)
+            // portal content is appended, and not known to be block-level
+            && !self
+                .portals
+                .iter()
+                .any(|(target, _)| node.attr("id").as_ref() == Some(target))
+    }
+
+    fn streaming(mode: Mode) -> Self {
+        Self {
+            streaming: true,
+            ..Self::new(mode)
+        }
+    }
+
+    /// Renders the chunks of resolved boundaries, or of all boundaries if `force` is set.
+    fn flush_suspended(&mut self, force: bool) -> String {
+        let (ready, suspended) = mem::take(&mut self.suspended)
+            .into_iter()
+            .partition::<Vec<_>, _>(|suspended| force || suspended.pending.get() == 0);
+        self.suspended = suspended;
+
+        let mut out = String::new();
+        for Suspended {
+            id,
+            node,
+            pending,
+            opaque,
+        } in ready
+        {
+            if !self.swap_script {
+                self.swap_script = true;
+                write_swap_script(&mut out).unwrap();
+            }
+
+            // resolved content is hydrated like any other, whereas a fallback is discarded
+            let resolved = !opaque && pending.get() == 0;
+            self.collect_portals(&node);
+            write!(out, "<template id=\"stardom-c{id}\">").unwrap();
+            let opaque = mem::replace(&mut self.opaque, !resolved);
+            if resolved && self.markers() {
+                write!(out, "<!--{}-->", markers::SUSPENSE_RESOLVED).unwrap();
+            }
+            render_contents(&mut out, self, &node).unwrap();
+            self.opaque = opaque;
+            write!(out, "</template><script>$stardomSwap({id})</script>").unwrap();
+            render_portals(&mut out, self).unwrap();
+            if resolved {
+                render_state(&mut out, self).unwrap();
+            }
+        }
+        out
+    }
+
+    /// Queues the portals beneath `node`, so that they are rendered within their target elements, and collects
+    /// the head nodes beneath it.
+    fn collect_portals(&mut self, node: &Node) {
+        match node.kind() {
+            NodeKind::Portal { id: Some(id), .. } => {
+                self.portals.push((id.clone(), node.clone()));
+            }
+            NodeKind::Head { .. } => {
+                self.heads.push(node.clone());
+                return;
+            }
+            _ => {}
+        }
+        for child in &*node.children_ref() {
+            self.collect_portals(child);
+        }
+    }
+
+    /// Takes the queued portals targeting `node`.
+    fn take_portals(&mut self, node: &Node) -> Vec<Node> {
+        let NodeKind::Element { attrs, .. } = node.kind() else {
+            return vec![];
+        };
+        let Some(id) = attrs.borrow().get("id").cloned() else {
+            return vec![];
+        };
+
+        let (targeted, rest) = mem::take(&mut self.portals)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(target, _)| *target == id);
+        self.portals = rest;
+        targeted.into_iter().map(|(_, portal)| portal).collect()
+    }
+}
+
+/// Writes the script defining `$stardomSwap`, which replaces a suspense placeholder and the fallback following
+/// it with the content of a streamed chunk.
+fn write_swap_script<W: Write>(w: &mut W) -> fmt::Result {
+    write!(
+        w,
+        "<script>function $stardomSwap(i){{\
+         var d=document,c=d.getElementById(\"stardom-c\"+i),p=d.getElementById(\"stardom-s\"+i);\
+         if(c)c.remove();if(!c||!p)return;\
+         for(var n=p.nextSibling,k=0;n;){{\
+         if(n.nodeType===8){{if(n.data===\"{start}\")k++;else if(n.data===\"{end}\"){{if(!k)break;k--}}}}\
+         var x=n.nextSibling;n.remove();n=x}}\
+         p.replaceWith(c.content)}}</script>",
+        start = markers::SUSPENSE_START,
+        end = markers::SUSPENSE_END,
+    )
+}
+
+/// Renders a whole tree, followed by the transferred state in hydration mode.
+fn render_root<W: Write>(w: &mut W, cx: &mut Cx, node: &Node) -> fmt::Result {
+    render_tree(w, cx, node)?;
+    render_state(w, cx)
+}
+
+/// Renders a whole tree, followed by any portals whose target element was not rendered.
+fn render_tree<W: Write>(w: &mut W, cx: &mut Cx, node: &Node) -> fmt::Result {
+    cx.collect_portals(node);
+    if cx.mode.contains(Mode::PRETTY) && is_block(node) {
+        cx.layout = true;
+        cx.fresh = true;
+    }
+    render_node(w, cx, node)?;
+    cx.layout = false;
+    render_portals(w, cx)
+}
+
+fn render_state<W: Write>(w: &mut W, cx: &mut Cx) -> fmt::Result {
+    if cx.mode.contains(Mode::HYDRATION) {
+        state::write_script(w)?;
+    }
+    Ok(())
+}
+
+/// Renders the head, body and transferred state of a [`Page`].
+fn render_page_parts(page: &mut Page, cx: &mut Cx, node: &Node) -> fmt::Result {
+    render_tree(&mut page.body, cx, node)?;
+    render_head(&mut page.head, cx)?;
+    render_state(&mut page.state, cx)
+}
+
+/// Renders the collected head nodes, skipping those followed by another with the same key.
+fn render_head<W: Write>(w: &mut W, cx: &mut Cx) -> fmt::Result {
+    let heads = mem::take(&mut cx.heads);
+    for (i, node) in heads.iter().enumerate() {
+        let NodeKind::Head { key, .. } = node.kind() else {
+            continue;
+        };
+        let replaced = key.is_some()
+            && heads[i + 1..].iter().any(
+                |other| matches!(other.kind(), NodeKind::Head { key: other, .. } if other == key),
+            );
+        if !replaced {
+            render_opaque_section(w, cx, markers::HEAD_START, markers::HEAD_END, node)?;
+        }
+    }
+    Ok(())
+}
+
+fn render_portals<W: Write>(w: &mut W, cx: &mut Cx) -> fmt::Result {
+    for (_, portal) in mem::take(&mut cx.portals) {
+        render_opaque_section(w, cx, markers::PORTAL_START, markers::PORTAL_END, &portal)?;
+    }
+    Ok(())
+}
 
+fn render_node<W: Write>(w: &mut W, cx: &mut Cx, node: &Node) -> fmt::Result {
     match node.kind() {
+        NodeKind::Element { .. } if cx.mode.contains(Mode::XML) => render_xml_element(w, cx, node),
         NodeKind::Element {
-            name,
-            namespace,
-            attrs,
+            name, namespace, ..
         } => {
-            let full_name = if let Some(ns) = namespace {
-                format!("{ns}:{name}")
-            } else {
-                name.clone()
-            };
+            newline(w, cx)?;
+            // foreign elements are serialized by their local name, with the namespace implied by the parser
+            write!(w, "<{name}")?;
+            for (key, value) in &node.kind().element_attrs() {
+                if value.is_empty() {
+                    write!(w, " {key}")?;
+                } else {
+                    write!(w, " {key}=\"{}\"", escape(value, true))?;
+                }
+            }
+            w.write_char('>')?;
 
-            let attr_str = attrs
-                .borrow()
-                .iter()
-                .map(|(key, value)| {
-                    let escaped = html_escape(value);
-                    format!(" {}=\"{}\"", key, escaped)
-                })
-                .collect::<Vec<_>>()
-                .join("");
-
-            if VOID.contains(&name.as_str()) {
-                write!(w, "<{full_name}{attr_str} />")?;
-                nl(w)
+            let Some(text) = element_text(name, namespace.as_deref()) else {
+                return Ok(());
+            };
+            if text != Text::Normal {
+                // text-only elements contain neither markers nor whitespace
+                let prev = (
+                    mem::replace(&mut cx.text, text),
+                    mem::replace(&mut cx.opaque, true),
+                    mem::replace(&mut cx.layout, false),
+                );
+                let result = match textarea_value(node) {
+                    Some(value) => w.write_str(&escape(&value, false)),
+                    // the content is only checked once whole, since an end tag may span several text nodes
+                    None if text == Text::Raw => {
+                        let mut content = String::new();
+                        render_contents(&mut content, cx, node)
+                            .and_then(|()| w.write_str(&escape_raw(&content, name)))
+                    }
+                    None => render_contents(w, cx, node),
+                };
+                (cx.text, cx.opaque, cx.layout) = prev;
+                result?;
             } else {
-                write!(w, "<{full_name}{attr_str}>")?;
-                nl(w)?;
-                render_children(w, mode, true, node)?;
-                write!(w, "</{full_name}>")?;
-                nl(w)
+                render_element_contents(w, cx, node)?;
             }
+            write!(w, "</{name}>")
         }
-        NodeKind::Text(content) => {
-            let escaped = html_escape(&content.borrow());
-            w.write_str(&escaped)?;
-            nl(w)
+        NodeKind::Text(content) if cx.mode.contains(Mode::XML) => {
+            w.write_str(&escape_xml(&content.borrow(), false))
         }
+        NodeKind::Text(content) => match cx.text {
+            Text::Raw => w.write_str(&content.borrow()),
+            Text::Normal | Text::Escapable => w.write_str(&escape(&content.borrow(), false)),
+        },
         NodeKind::Raw(content) => {
-            if mode.contains(Mode::HYDRATION) {
+            if cx.markers() {
                 write!(
                     w,
-                    "<!--stardom:raw-->{}<!--stardom:raw-->",
-                    content.borrow()
-                )?;
+                    "<!--{}-->{}<!--{}-->",
+                    markers::RAW,
+                    content.borrow(),
+                    markers::RAW
+                )
             } else {
-                w.write_str(&content.borrow())?;
+                w.write_str(&content.borrow())
             }
-            nl(w)
         }
-        NodeKind::Fragment | NodeKind::Component(_) => render_children(w, mode, false, node),
+        NodeKind::Fragment if cx.markers() => {
+            render_section(w, cx, markers::FRAGMENT_START, markers::FRAGMENT_END, node)
+        }
+        NodeKind::Component(component) if cx.markers() && !component.transparent => render_section(
+            w,
+            cx,
+            markers::COMPONENT_START,
+            markers::COMPONENT_END,
+            node,
+        ),
+        NodeKind::Fragment | NodeKind::Component(_) => render_contents(w, cx, node),
+        NodeKind::ServerOnly => {
+            render_opaque_section(w, cx, markers::SERVER_START, markers::SERVER_END, node)
+        }
+        NodeKind::ClientOnly(_) => {
+            render_opaque_section(w, cx, markers::CLIENT_START, markers::CLIENT_END, node)
+        }
+        NodeKind::Suspense { pending, .. } if cx.streaming && pending.get() > 0 => {
+            let id = cx.next_id;
+            cx.next_id += 1;
+            cx.suspended.push(Suspended {
+                id,
+                node: node.clone(),
+                pending: *pending,
+                opaque: cx.opaque,
+            });
+
+            // the markers are required by the swap script, even outside of hydration mode
+            write!(
+                w,
+                "<!--{}--><template id=\"stardom-s{id}\"></template>",
+                markers::SUSPENSE_START
+            )?;
+            let opaque = mem::replace(&mut cx.opaque, true);
+            render_contents(w, cx, node)?;
+            cx.opaque = opaque;
+            write!(w, "<!--{}-->", markers::SUSPENSE_END)
+        }
+        NodeKind::Suspense { pending, .. } if pending.get() == 0 && cx.markers() => {
+            newline(w, cx)?;
+            write!(
+                w,
+                "<!--{}--><!--{}-->",
+                markers::SUSPENSE_START,
+                markers::SUSPENSE_RESOLVED
+            )?;
+            render_contents(w, cx, node)?;
+            newline(w, cx)?;
+            write!(w, "<!--{}-->", markers::SUSPENSE_END)
+        }
+        NodeKind::Suspense { .. } => {
+            render_opaque_section(w, cx, markers::SUSPENSE_START, markers::SUSPENSE_END, node)
+        }
+        // rendered within their target element instead
+        NodeKind::Portal { .. } => Ok(()),
+        // rendered into the head string instead
+        NodeKind::Head { .. } => Ok(()),
     }
 }
 
-fn render_children<W: Write>(w: &mut W, mode: Mode, indent: bool, node: &Node) -> fmt::Result {
-    if indent && mode.contains(Mode::PRETTY) {
-        let mut buf = String::new();
-        for child in &*node.children_ref() {
-            render_node(&mut buf, mode, child).unwrap();
+/// Returns the `value` property of a textarea, which is rendered in place of its children.
+fn textarea_value(node: &Node) -> Option<String> {
+    match node.kind() {
+        NodeKind::Element {
+            name, directives, ..
+        } if name == "textarea" => directives
+            .props
+            .borrow()
+            .get("value")
+            .and_then(|value| value.to_attr().map(Cow::into_owned)),
+        _ => None,
+    }
+}
+
+/// Renders the children of an element, on their own lines if they are [laid out](Cx::is_layout).
+fn render_element_contents<W: Write>(w: &mut W, cx: &mut Cx, node: &Node) -> fmt::Result {
+    if cx.is_layout(node) {
+        let layout = mem::replace(&mut cx.layout, true);
+        cx.depth += 1;
+        let result = render_contents(w, cx, node);
+        cx.depth -= 1;
+        result?;
+        newline(w, cx)?;
+        cx.layout = layout;
+    } else {
+        let layout = mem::replace(&mut cx.layout, false);
+        let result = render_contents(w, cx, node);
+        cx.layout = layout;
+        result?;
+    }
+    Ok(())
+}
+
+fn render_xml_element<W: Write>(w: &mut W, cx: &mut Cx, node: &Node) -> fmt::Result {
+    let NodeKind::Element {
+        name, namespace, ..
+    } = node.kind()
+    else {
+        unreachable!();
+    };
+    let namespace = namespace.clone().unwrap_or_else(|| {
+        match name.as_str() {
+            "svg" => SVG_NAMESPACE,
+            "math" => MATHML_NAMESPACE,
+            _ => cx.namespace.as_deref().unwrap_or(HTML_NAMESPACE),
         }
+        .to_string()
+    });
 
-        for line in buf.lines() {
-            writeln!(w, "  {}", line)?;
+    newline(w, cx)?;
+    write!(w, "<{name}")?;
+    if cx.xmlns.as_ref() != Some(&namespace) {
+        write!(w, " xmlns=\"{}\"", escape_xml(&namespace, true))?;
+    }
+    let attrs = node.kind().element_attrs();
+    if attrs.keys().any(|key| key.starts_with("xlink:")) {
+        write!(w, " xmlns:xlink=\"{XLINK_NAMESPACE}\"")?;
+    }
+    for (key, value) in &attrs {
+        // namespace declarations are derived from the elements instead
+        if key != "xmlns" && !key.starts_with("xmlns:") {
+            write!(w, " {key}=\"{}\"", escape_xml(value, true))?;
         }
-    } else {
-        for child in &*node.children_ref() {
-            render_node(w, mode, child)?;
+    }
+
+    let inherited = match name.as_str() {
+        "foreignObject" => HTML_NAMESPACE.to_string(),
+        _ => namespace.clone(),
+    };
+    if let Some(value) = textarea_value(node) {
+        return write!(w, ">{}</{name}>", escape_xml(&value, false));
+    }
+    if !has_output(cx, node, cx.markers()) {
+        return w.write_str("/>");
+    }
+
+    w.write_char('>')?;
+    let prev = (cx.namespace.replace(inherited), cx.xmlns.replace(namespace));
+    let result = render_element_contents(w, cx, node);
+    (cx.namespace, cx.xmlns) = prev;
+    result?;
+    write!(w, "</{name}>")
+}
+
+/// Returns whether rendering the children of `node` writes anything, in which case its XML element is not
+/// self-closed. `markers` is whether fragments and raw content are delimited by [markers](Cx::markers).
+fn has_output(cx: &Cx, node: &Node, markers: bool) -> bool {
+    let hydration = cx.mode.contains(Mode::HYDRATION);
+    let portals = match node.kind() {
+        NodeKind::Element { attrs, .. } => {
+            let id = attrs.borrow().get("id").cloned();
+            cx.portals.iter().any(|(target, portal)| {
+                Some(target) == id.as_ref() && (hydration || has_output(cx, portal, false))
+            })
+        }
+        _ => false,
+    };
+
+    portals
+        || node.children_ref().iter().any(|child| match child.kind() {
+            NodeKind::Element { .. } => true,
+            NodeKind::Text(content) => !content.borrow().is_empty(),
+            NodeKind::Raw(content) => markers || !content.borrow().is_empty(),
+            NodeKind::Component(component) if component.transparent => {
+                has_output(cx, child, markers)
+            }
+            NodeKind::Fragment | NodeKind::Component(_) => {
+                markers || has_output(cx, child, markers)
+            }
+            NodeKind::Suspense { pending, .. } if cx.streaming && pending.get() > 0 => true,
+            NodeKind::Suspense { pending, .. } if pending.get() == 0 && markers => true,
+            NodeKind::ServerOnly | NodeKind::ClientOnly(_) | NodeKind::Suspense { .. } => {
+                hydration || has_output(cx, child, false)
+            }
+            NodeKind::Portal { .. } | NodeKind::Head { .. } => false,
+        })
+}
+
+/// Renders the children of `node`, delimited by comment markers in hydration mode.
+fn render_section<W: Write>(
+    w: &mut W,
+    cx: &mut Cx,
+    start: &str,
+    end: &str,
+    node: &Node,
+) -> fmt::Result {
+    if !cx.mode.contains(Mode::HYDRATION) {
+        return render_contents(w, cx, node);
+    }
+
+    newline(w, cx)?;
+    write!(w, "<!--{start}-->")?;
+    render_contents(w, cx, node)?;
+    newline(w, cx)?;
+    write!(w, "<!--{end}-->")
+}
+
+/// Like [`render_section`], but for sections which the hydrator adopts or discards as a whole, rather than
+/// hydrating their content.
+fn render_opaque_section<W: Write>(
+    w: &mut W,
+    cx: &mut Cx,
+    start: &str,
+    end: &str,
+    node: &Node,
+) -> fmt::Result {
+    let opaque = mem::replace(&mut cx.opaque, true);
+    let result = render_section(w, cx, start, end, node);
+    cx.opaque = opaque;
+    result
+}
+
+/// Renders the children of `node`, followed by the portals targeting it.
+fn render_contents<W: Write>(w: &mut W, cx: &mut Cx, node: &Node) -> fmt::Result {
+    let portals = cx.take_portals(node);
+    let mut after_text = false;
+    for child in &*node.children_ref() {
+        match child.kind() {
+            NodeKind::Text(content) if !content.borrow().is_empty() => {
+                if after_text && cx.markers() {
+                    write!(w, "<!--{}-->", markers::TEXT)?;
+                }
+                after_text = true;
+            }
+            // neither produces any output
+            NodeKind::Text(_) | NodeKind::Portal { .. } | NodeKind::Head { .. } => {}
+            _ => after_text = false,
+        }
+        render_node(w, cx, child)?;
+    }
+    for portal in portals {
+        render_opaque_section(w, cx, markers::PORTAL_START, markers::PORTAL_END, &portal)?;
+    }
+    Ok(())
+}
+
+/// Starts a new indented line if the nodes being rendered are [laid out](Cx::layout).
+fn newline<W: Write>(w: &mut W, cx: &mut Cx) -> fmt::Result {
+    if cx.layout {
+        if !mem::take(&mut cx.fresh) {
+            w.write_char('\n')?;
+        }
+        for _ in 0..cx.depth {
+            w.write_str("  ")?;
         }
     }
     Ok(())
 }
 
-// See https://cheatsheetseries.owasp.org/cheatsheets/Cross_Site_Scripting_Prevention_Cheat_Sheet.html#output-encoding-for-html-contexts
-fn html_escape(s: &str) -> String {
+/// Escapes text or an attribute value for XML, which only predefines a few entities.
+fn escape_xml(s: &str, attr: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '"' if attr => out.push_str("&quot;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Returns whether `node` renders nothing but block-level elements.
+fn is_block(node: &Node) -> bool {
+    match node.kind() {
+        NodeKind::Element {
+            name, namespace, ..
+        } => {
+            namespace.as_deref().is_none_or(|ns| ns == HTML_NAMESPACE)
+                && BLOCK.contains(&name.as_str())
+        }
+        NodeKind::Text(content) => content.borrow().is_empty(),
+        NodeKind::Fragment | NodeKind::Component(_) => node.children_ref().iter().all(is_block),
+        NodeKind::Portal { .. } | NodeKind::Head { .. } => true,
+        _ => false,
+    }
+}
+
+/// Neutralizes end tags of the raw text element `name` within its content, which would otherwise close the element
+/// early and let the rest be parsed as markup.
+fn escape_raw(s: &str, name: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find("</") {
+        out.push_str(&rest[..i]);
+        rest = &rest[i + 2..];
+        if rest
+            .get(..name.len())
+            .is_some_and(|tag| tag.eq_ignore_ascii_case(name))
+        {
+            out.push_str("<\\/");
+        } else {
+            out.push_str("</");
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Escapes text or an attribute value, following the HTML serialization algorithm.
+///
+/// See <https://html.spec.whatwg.org/multipage/parsing.html#escapingString>.
+fn escape(s: &str, attr: bool) -> String {
     let mut out = String::with_capacity(s.len());
     for c in s.chars() {
         match c {
             '&' => out.push_str("&amp;"),
+            '\u{a0}' => out.push_str("&nbsp;"),
+            '"' if attr => out.push_str("&quot;"),
             '<' => out.push_str("&lt;"),
             '>' => out.push_str("&gt;"),
-            '"' => out.push_str("&quot;"),
-            '\'' => out.push_str("&#x27;"),
             _ => out.push(c),
         }
     }
     out
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{head, state::transfer};
+
+    #[test]
+    fn document_splices_page_into_template() {
+        let page = render_page(Mode::HYDRATION, || {
+            let main = Node::element("main".to_string());
+            main.insert(&head::title(|| "Todos".to_string()), None);
+            main.insert(&Node::text(transfer(|| "todos".to_string())), None);
+            main
+        });
+        let shell = Shell::Template(
+            "<!DOCTYPE html><html><head><link rel=\"icon\" href=\"/icon.png\"></head>\
+             <body><div id=\"app\"><!--stardom:body--></div></body></html>"
+                .to_string(),
+        );
+
+        assert_eq!(
+            render_document(&shell, &page, Some(&Assets::new("/dist/", "app"))),
+            "<!DOCTYPE html><html><head><link rel=\"icon\" href=\"/icon.png\">\
+             <!--stardom:head--><title>Todos</title><!--/stardom:head-->\
+             <link rel=\"modulepreload\" href=\"/dist/app.js\">\
+             <link rel=\"preload\" href=\"/dist/app_bg.wasm\" as=\"fetch\" type=\"application/wasm\" crossorigin>\
+             </head><body><div id=\"app\"><main>todos</main></div>\
+             <script type=\"application/json\" id=\"stardom-state\">{\":0\":\"todos\"}</script>\
+             <script type=\"module\">import init from \"/dist/app.js\";init(\"/dist/app_bg.wasm\");</script>\
+             </body></html>"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "in that order")]
+    fn document_template_requires_ordered_markers() {
+        let page = render_page(Mode::HYDRATION, Node::fragment);
+        let shell = Shell::Template(
+            "<html><head><!--stardom:body--></head><body></body></html>".to_string(),
+        );
+        render_document(&shell, &page, None);
+    }
+
+    fn element(name: &str, attrs: &[(&str, &str)], children: &[Node]) -> Node {
+        let node = Node::element(name.to_string());
+        for (key, value) in attrs {
+            node.set_attr(key.to_string(), value.to_string());
+        }
+        for child in children {
+            node.insert(child, None);
+        }
+        node
+    }
+
+    fn text(value: &str) -> Node {
+        Node::text(value.to_string())
+    }
+
+    #[test]
+    fn serializes_html_per_spec() {
+        let html = render_to_string(Mode::HYDRATION, || {
+            let svg = Node::element_ns("svg".to_string(), "http://www.w3.org/2000/svg".to_string());
+            svg.insert(
+                &Node::element_ns("path".to_string(), "http://www.w3.org/2000/svg".to_string()),
+                None,
+            );
+            element(
+                "form",
+                &[],
+                &[
+                    element("input", &[("disabled", ""), ("value", "\"a\" & <b>")], &[]),
+                    element("br", &[], &[]),
+                    element("script", &[], &[text("if (a < b && c) {}"), text("</b>")]),
+                    element("textarea", &[], &[text("<b>"), text("&nbsp;\u{a0}")]),
+                    svg,
+                    text("1 < 2 & 3 > 2"),
+                ],
+            )
+        });
+        assert_eq!(
+            html,
+            "<form><input disabled value=\"&quot;a&quot; &amp; &lt;b&gt;\"><br>\
+             <script>if (a < b && c) {}</b></script>\
+             <textarea>&lt;b&gt;&amp;nbsp;&nbsp;</textarea>\
+             <svg><path></path></svg>1 &lt; 2 &amp; 3 &gt; 2</form>"
+        );
+    }
+
+    #[test]
+    fn raw_text_cannot_close_its_element() {
+        let html = render_to_string(Mode::empty(), || {
+            element(
+                "div",
+                &[],
+                &[
+                    element(
+                        "script",
+                        &[],
+                        &[
+                            text("let s = \"</SCRIPT><img src=x>\"; <"),
+                            text("/script>"),
+                        ],
+                    ),
+                    element("style", &[], &[text("a::after { content: \"</style>\" }")]),
+                ],
+            )
+        });
+        assert_eq!(
+            html,
+            "<div><script>let s = \"<\\/SCRIPT><img src=x>\"; <\\/script></script>\
+             <style>a::after { content: \"<\\/style>\" }</style></div>"
+        );
+    }
+
+    #[test]
+    fn pretty_preserves_inline_and_preformatted_content() {
+        let html = render_to_string(Mode::PRETTY, || {
+            element(
+                "main",
+                &[],
+                &[
+                    element(
+                        "p",
+                        &[],
+                        &[
+                            text("a "),
+                            element("b", &[], &[text("bold")]),
+                            text(" word"),
+                        ],
+                    ),
+                    element(
+                        "div",
+                        &[],
+                        &[element("pre", &[], &[text("fn main() {\n    run();\n}")])],
+                    ),
+                    element("section", &[], &[element("hr", &[], &[])]),
+                ],
+            )
+        });
+        assert_eq!(
+            html,
+            "<main>\n  <p>a <b>bold</b> word</p>\n  <div><pre>fn main() {\n    run();\n}</pre></div>\n  \
+             <section>\n    <hr>\n  </section>\n</main>"
+        );
+    }
+
+    #[test]
+    fn xml_declares_namespaces_and_self_closes() {
+        let xml = render_xml_document(Mode::empty(), || {
+            let textarea = element("textarea", &[], &[]);
+            textarea.set_prop("value", "<note>");
+            let object = element(
+                "foreignObject",
+                &[],
+                &[element("p", &[], &[text("a\u{a0}& b")]), textarea],
+            );
+            element(
+                "svg",
+                &[("viewBox", "0 0 10 10")],
+                &[
+                    element("use", &[("xlink:href", "#dot")], &[]),
+                    element("g", &[], &[Node::fragment()]),
+                    object,
+                    element("title", &[], &[text("<chart>")]),
+                ],
+            )
+        });
+        assert_eq!(
+            xml,
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 10 10\">\
+             <use xmlns:xlink=\"http://www.w3.org/1999/xlink\" xlink:href=\"#dot\"/><g/>\
+             <foreignObject><p xmlns=\"http://www.w3.org/1999/xhtml\">a\u{a0}&amp; b</p>\
+             <textarea xmlns=\"http://www.w3.org/1999/xhtml\">&lt;note&gt;</textarea></foreignObject>\
+             <title>&lt;chart&gt;</title></svg>"
+        );
+    }
+}
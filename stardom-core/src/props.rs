@@ -0,0 +1,191 @@
+use std::borrow::Cow;
+
+use wasm_bindgen::JsValue;
+
+/// A value assigned to a DOM property by [`Node::set_prop`](crate::Node::set_prop).
+///
+/// Values other than [`PropValue::Js`] can be serialized, so that properties reflecting an attribute are
+/// rendered as that attribute.
+#[derive(Clone, Debug)]
+pub enum PropValue {
+    Bool(bool),
+    Number(f64),
+    String(String),
+    /// Any other value, which is only assigned in the browser.
+    Js(JsValue),
+}
+
+impl PropValue {
+    pub(crate) fn to_js(&self) -> JsValue {
+        match self {
+            Self::Bool(value) => JsValue::from_bool(*value),
+            Self::Number(value) => JsValue::from_f64(*value),
+            Self::String(value) => JsValue::from_str(value),
+            Self::Js(value) => value.clone(),
+        }
+    }
+
+    /// Returns the value of the attribute reflecting this value, or `None` if the attribute is absent or the
+    /// value cannot be serialized.
+    pub(crate) fn to_attr(&self) -> Option<Cow<'_, str>> {
+        match self {
+            Self::Bool(value) => value.then_some(Cow::Borrowed("")),
+            Self::Number(value) => Some(Cow::Owned(value.to_string())),
+            Self::String(value) => Some(Cow::Borrowed(value)),
+            Self::Js(_) => None,
+        }
+    }
+}
+
+impl From<bool> for PropValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+macro_rules! impl_number {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for PropValue {
+                fn from(value: $ty) -> Self {
+                    Self::Number(value.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_number!(i8, i16, i32, u8, u16, u32, f32, f64);
+
+impl From<String> for PropValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<&str> for PropValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<&String> for PropValue {
+    fn from(value: &String) -> Self {
+        Self::String(value.clone())
+    }
+}
+
+impl From<JsValue> for PropValue {
+    fn from(value: JsValue) -> Self {
+        Self::Js(value)
+    }
+}
+
+/// Properties which reflect an attribute, along with the name of that attribute.
+///
+/// `value` only sets the initial value of form controls, but is reflected so that rendered controls start out
+/// with the value of the property.
+const REFLECTED: &[(&str, &str)] = &[
+    ("value", "value"),
+    ("checked", "checked"),
+    ("selected", "selected"),
+    ("disabled", "disabled"),
+    ("hidden", "hidden"),
+    ("multiple", "multiple"),
+    ("required", "required"),
+    ("readOnly", "readonly"),
+    ("open", "open"),
+    ("muted", "muted"),
+    ("id", "id"),
+    ("title", "title"),
+    ("lang", "lang"),
+    ("tabIndex", "tabindex"),
+    ("htmlFor", "for"),
+    ("className", "class"),
+];
+
+/// Returns the attribute reflected by the property `name`, if any.
+pub(crate) fn reflected_attr(name: &str) -> Option<&'static str> {
+    REFLECTED
+        .iter()
+        .find(|(prop, _)| *prop == name)
+        .map(|(_, attr)| *attr)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        browser::mount,
+        dom::virt::Document,
+        render::{render_to_string, Mode},
+        Node,
+    };
+
+    #[test]
+    fn reflected_props_are_rendered() {
+        let html = render_to_string(Mode::empty(), || {
+            let input = Node::element("input".into());
+            input.set_attr("value".into(), "initial".into());
+            input.set_prop("value", "edited");
+            input.set_prop("checked", true);
+            input.set_prop("indeterminate", true);
+            input.set_prop("disabled", false);
+
+            let textarea = Node::element("textarea".into());
+            textarea.insert(&Node::text("initial".into()), None);
+            textarea.set_prop("value", "<edited>");
+
+            let div = Node::element("div".into());
+            div.insert(&input, None);
+            div.insert(&textarea, None);
+            div
+        });
+        assert_eq!(
+            html,
+            "<div><input value=\"edited\" checked><textarea>&lt;edited&gt;</textarea></div>"
+        );
+    }
+
+    #[test]
+    fn class_name_is_merged_with_classes() {
+        let element = || {
+            let div = Node::element("div".into());
+            div.set_class("active", true);
+            div.set_prop("className", "item");
+            div
+        };
+        let html = render_to_string(Mode::empty(), element);
+        assert_eq!(html, "<div class=\"item active\"></div>");
+
+        let document = Document::new();
+        let mut div = None;
+        let _mount = mount(document.body(), || div.insert(element()).clone());
+        let div = div.unwrap();
+        assert_eq!(document.body().inner_html(), html);
+
+        div.set_prop("className", "entry");
+        div.set_class("done", true);
+        assert_eq!(
+            document.body().inner_html(),
+            "<div class=\"entry active done\"></div>"
+        );
+    }
+
+    #[test]
+    fn virtual_textarea_values_are_content() {
+        let document = Document::new();
+        let mut textarea = None;
+        let _mount = mount(document.body(), || {
+            let node = Node::element("textarea".into());
+            node.set_prop("value", "initial");
+            textarea.insert(node).clone()
+        });
+        assert_eq!(document.body().inner_html(), "<textarea>initial</textarea>");
+
+        textarea.unwrap().set_prop("value", "<edited>");
+        assert_eq!(
+            document.body().inner_html(),
+            "<textarea>&lt;edited&gt;</textarea>"
+        );
+    }
+}
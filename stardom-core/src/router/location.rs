@@ -0,0 +1,223 @@
+use std::{fmt, str::FromStr};
+
+use super::path::{split, ParamError};
+
+/// A parsed URL, relative to the origin.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct Location {
+    pub path: String,
+    pub query: Query,
+    pub hash: String,
+}
+
+impl Location {
+    /// Parses a URL such as `/users/4?tab=posts#top`.
+    ///
+    /// If the URL includes a scheme and host, they are ignored.
+    pub fn parse(url: &str) -> Self {
+        let url = match url.split_once("://") {
+            Some((_, rest)) => rest.find('/').map_or("/", |i| &rest[i..]),
+            None => url,
+        };
+
+        let (url, hash) = url.split_once('#').unwrap_or((url, ""));
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+
+        Self {
+            path: if path.starts_with('/') {
+                path.to_string()
+            } else {
+                format!("/{path}")
+            },
+            query: Query::parse(query),
+            hash: hash.to_string(),
+        }
+    }
+
+    /// Resolves `url` against this location, like a browser resolves the `href` of an anchor against the page.
+    ///
+    /// Absolute paths and URLs are [parsed](Self::parse) as is. A relative path replaces the last segment of
+    /// this path, while a URL consisting of only a query or hash replaces just the query and hash, or the hash.
+    pub fn resolve(&self, url: &str) -> Self {
+        if has_scheme(url) || (url.starts_with('/') && !url.starts_with("//")) {
+            return Self::parse(url);
+        }
+        if let Some(rest) = url.strip_prefix("//") {
+            // scheme-relative URLs include a host, which is ignored
+            return Self::parse(rest.find('/').map_or("/", |i| &rest[i..]));
+        }
+        if let Some(hash) = url.strip_prefix('#') {
+            return Self {
+                hash: hash.to_string(),
+                ..self.clone()
+            };
+        }
+
+        let (url, hash) = url.split_once('#').unwrap_or((url, ""));
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        let path = if path.is_empty() {
+            self.path.clone()
+        } else {
+            let base = &self.path[..self.path.rfind('/').map_or(0, |i| i + 1)];
+            remove_dot_segments(&format!("{base}{path}"))
+        };
+
+        Self {
+            path,
+            query: Query::parse(query),
+            hash: hash.to_string(),
+        }
+    }
+
+    /// Returns the decoded, non-empty segments of the path.
+    pub fn segments(&self) -> Vec<String> {
+        split(&self.path).map(decode).collect()
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.path)?;
+        if !self.query.is_empty() {
+            write!(f, "?{}", self.query)?;
+        }
+        if !self.hash.is_empty() {
+            write!(f, "#{}", self.hash)?;
+        }
+        Ok(())
+    }
+}
+
+/// The decoded key-value pairs of a query string.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct Query(Vec<(String, String)>);
+
+impl Query {
+    pub fn parse(query: &str) -> Self {
+        let query = query.strip_prefix('?').unwrap_or(query);
+        Self(
+            query
+                .split('&')
+                .filter(|pair| !pair.is_empty())
+                .map(|pair| {
+                    let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                    (decode_component(key), decode_component(value))
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns the first value associated with `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> {
+        self.0
+            .iter()
+            .filter(move |(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn parse_value<T: FromStr>(&self, key: &str) -> Result<T, ParamError> {
+        let value = self
+            .get(key)
+            .ok_or_else(|| ParamError::Missing(key.to_string()))?;
+        value.parse().map_err(|_| ParamError::Invalid {
+            key: key.to_string(),
+            value: value.to_string(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (key, value)) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("&")?;
+            }
+            write!(f, "{}={}", encode(key), encode(value))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns whether `url` starts with a scheme, such as `https:` or `mailto:`.
+pub(crate) fn has_scheme(url: &str) -> bool {
+    let Some((scheme, _)) = url.split_once(':') else {
+        return false;
+    };
+    let mut chars = scheme.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+/// Resolves the `.` and `..` segments of an absolute path.
+fn remove_dot_segments(path: &str) -> String {
+    let mut segments = vec![];
+    let mut parts = path.split('/').skip(1).peekable();
+    while let Some(part) = parts.next() {
+        match part {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(part),
+        }
+        // a trailing dot segment refers to a directory
+        if parts.peek().is_none() && matches!(part, "." | "..") {
+            segments.push("");
+        }
+    }
+    format!("/{}", segments.join("/"))
+}
+
+/// Decodes percent-encoded sequences, replacing invalid UTF-8 with `U+FFFD`.
+pub(crate) fn decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = |b: u8| (b as char).to_digit(16);
+        match bytes[i..] {
+            [b'%', hi, lo, ..] if hex(hi).is_some() && hex(lo).is_some() => {
+                out.push((hex(hi).unwrap() * 16 + hex(lo).unwrap()) as u8);
+                i += 3;
+            }
+            [b, ..] => {
+                out.push(b);
+                i += 1;
+            }
+            [] => unreachable!(),
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn decode_component(s: &str) -> String {
+    decode(&s.replace('+', " "))
+}
+
+fn encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{b:02X}")),
+        }
+    }
+    out
+}
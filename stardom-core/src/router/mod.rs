@@ -0,0 +1,504 @@
+mod location;
+mod path;
+
+pub use self::{
+    location::{Location, Query},
+    path::{FromParams, ParamError, Params, Pattern},
+};
+
+use std::rc::Rc;
+
+use stardom_reactive::{memo, signal, untrack, Input, Memo, Output, Signal};
+use wasm_bindgen::{prelude::*, JsCast};
+
+use crate::{
+    component::{register_context, try_context},
+    dom,
+    env::{is_browser, is_hydrating},
+    events::EventOptions,
+    flow::switch,
+    named::events::click,
+    node::{IntoNode, Node},
+    util::window,
+};
+
+/// A route within a [`Router`].
+///
+/// The patterns of child routes are matched against the remainder of the path left by their parent. A route
+/// with children renders its matched child wherever it calls [`outlet`].
+pub struct Route {
+    pattern: Pattern,
+    render: Rc<dyn Fn() -> Node>,
+    children: Vec<Self>,
+}
+
+impl Route {
+    pub fn new<P, F>(pattern: P, render: F) -> Self
+    where
+        P: Into<Pattern>,
+        F: Fn() -> Node + 'static,
+    {
+        Self {
+            pattern: pattern.into(),
+            render: Rc::new(render),
+            children: vec![],
+        }
+    }
+
+    pub fn child(mut self, route: Self) -> Self {
+        self.children.push(route);
+        self
+    }
+}
+
+/// Renders the route matching the current location.
+///
+/// Routes are tried in order, and the first to match the whole path is rendered. In the browser, the location
+/// is read from `window.location` and kept in sync with the History API. When rendering on the server, the
+/// location is taken from the request URL provided by [`with_url`]. In a [virtual](crate::dom::virt) DOM, the
+/// location starts at the URL provided by [`with_url`], or `/` without one, and is only kept by the router.
+///
+/// ```ignore
+/// Router::new()
+///     .route(Route::new("/", home))
+///     .route(
+///         Route::new("/users", users_layout)
+///             .child(Route::new("", user_list))
+///             .child(Route::new(":id", user)),
+///     )
+///     .route(Route::new("/files/*path", file))
+///     .fallback(not_found)
+/// ```
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+    fallback: Option<Rc<dyn Fn() -> Node>>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn route(mut self, route: Route) -> Self {
+        self.routes.push(route);
+        self
+    }
+
+    /// Sets the node rendered when no route matches.
+    pub fn fallback<F>(mut self, render: F) -> Self
+    where
+        F: Fn() -> Node + 'static,
+    {
+        self.fallback = Some(Rc::new(render));
+        self
+    }
+}
+
+impl IntoNode for Router {
+    fn into_node(self) -> Node {
+        Node::component(move || {
+            let client = is_browser() || is_hydrating();
+            let web = has_window();
+
+            let location = signal(if web {
+                browser_location()
+            } else {
+                try_context::<RequestUrl>()
+                    .map(|RequestUrl(url)| Location::parse(&url))
+                    .or_else(|| client.then(|| Location::parse("/")))
+                    .expect("no request URL provided outside of the browser (see `with_url`)")
+            });
+
+            let routes: Rc<[Route]> = self.routes.into();
+            let matches = memo({
+                let routes = routes.clone();
+                move || location.with(|location| match_routes(&routes, &location.segments()))
+            });
+
+            register_context(RouterContext {
+                routes,
+                fallback: self.fallback,
+                location,
+                matches,
+                _listener: web.then(|| Rc::new(PopStateListener::new(location))),
+            });
+
+            outlet()
+        })
+    }
+}
+
+/// Provides the URL of the current request to routers rendered outside of the browser.
+pub fn with_url<U, F>(url: U, f: F) -> Node
+where
+    U: Into<String>,
+    F: FnOnce() -> Node,
+{
+    let url = url.into();
+    Node::component(move || {
+        register_context(RequestUrl(url));
+        f()
+    })
+}
+
+/// Renders the matched child of the current route, or the matched top-level route if called directly within
+/// a [`Router`].
+pub fn outlet() -> Node {
+    let router = router();
+    let depth = try_context::<Depth>().map_or(0, |Depth(depth)| depth);
+    let matches = router.matches;
+
+    switch(
+        move || matches.with(|matches| matches.as_ref()?.get(depth).cloned()),
+        move |matched| match matched {
+            Some(matched) => {
+                let render = router.route(&matched.route).render.clone();
+                let params = matched.params.clone();
+                Node::component(move || {
+                    register_context(Depth(depth + 1));
+                    register_context(params);
+                    render()
+                })
+            }
+            None => match &router.fallback {
+                Some(fallback) if depth == 0 => fallback(),
+                _ => Node::fragment(),
+            },
+        },
+    )
+}
+
+/// Returns the parameters captured by the current route and its parents.
+pub fn params() -> Params {
+    try_context().unwrap_or_default()
+}
+
+/// Parses the parameters captured by the current route and its parents.
+pub fn typed_params<T: FromParams>() -> Result<T, ParamError> {
+    T::from_params(&params())
+}
+
+/// Returns the current location of the enclosing router.
+pub fn location() -> Memo<Location> {
+    let location = router().location;
+    memo(move || location.cloned())
+}
+
+/// Returns the query string of the current location.
+pub fn query() -> Memo<Query> {
+    let location = router().location;
+    memo(move || location.with(|location| location.query.clone()))
+}
+
+/// Returns a [`Navigator`] for the enclosing router.
+pub fn navigator() -> Navigator {
+    Navigator {
+        location: router().location,
+    }
+}
+
+/// Changes the location of a [`Router`].
+#[derive(Clone, Copy)]
+pub struct Navigator {
+    location: Signal<Location>,
+}
+
+impl Navigator {
+    /// Navigates to `url`, [resolved](Location::resolve) against the current location, adding a new entry to the
+    /// session history.
+    pub fn push(&self, url: &str) {
+        self.navigate(url, false);
+    }
+
+    /// Navigates to `url`, [resolved](Location::resolve) against the current location, replacing the current
+    /// entry in the session history.
+    pub fn replace(&self, url: &str) {
+        self.navigate(url, true);
+    }
+
+    fn navigate(&self, url: &str, replace: bool) {
+        let location = untrack(|| self.location.with(|current| current.resolve(url)));
+
+        if has_window() {
+            let history = window().history().unwrap();
+            let url = location.to_string();
+            if replace {
+                history.replace_state_with_url(&JsValue::NULL, "", Some(&url))
+            } else {
+                history.push_state_with_url(&JsValue::NULL, "", Some(&url))
+            }
+            .unwrap();
+        }
+
+        self.location.set(location);
+    }
+}
+
+/// Creates an anchor which navigates the enclosing router when clicked.
+///
+/// Clicks are left to the browser when they would normally open a new tab or window, when the anchor has a
+/// `target` other than `_self`, when `href` has a scheme such as `https:` or `mailto:`, or points to another
+/// host, and when `href` only consists of a hash, so that the browser scrolls to its anchor. Other hrefs are
+/// resolved against the current location.
+pub fn link<H, N>(href: H, children: N) -> Node
+where
+    H: Into<String>,
+    N: IntoNode,
+{
+    let href = href.into();
+    let node = Node::element("a".into());
+    node.set_attr("href".into(), href.clone());
+    node.insert(&children.into_node(), None);
+
+    if is_browser() || is_hydrating() {
+        let navigator = navigator();
+        node.event(&click, EventOptions::new(), move |ev| {
+            // virtual events carry no modifiers, so they act as a plain left click
+            let (modified, button) = ev.as_web().map_or((false, 0), |ev| {
+                let modified = ev.meta_key() || ev.ctrl_key() || ev.shift_key() || ev.alt_key();
+                (modified, ev.button())
+            });
+            let target = ev
+                .current_target()
+                .and_then(|target| target.attribute("target"))
+                .filter(|target| target != "_self");
+            let external = href.starts_with("//") || location::has_scheme(&href);
+
+            if ev.default_prevented()
+                || button != 0
+                || modified
+                || target.is_some()
+                || external
+                || href.starts_with('#')
+            {
+                return;
+            }
+
+            ev.prevent_default();
+            navigator.push(&href);
+        })
+        .forget();
+    }
+
+    node
+}
+
+#[derive(Clone)]
+struct RouterContext {
+    routes: Rc<[Route]>,
+    fallback: Option<Rc<dyn Fn() -> Node>>,
+    location: Signal<Location>,
+    matches: Memo<Option<Vec<Matched>>>,
+    _listener: Option<Rc<PopStateListener>>,
+}
+
+impl RouterContext {
+    fn route(&self, ids: &[usize]) -> &Route {
+        let (first, rest) = ids.split_first().unwrap();
+        rest.iter()
+            .fold(&self.routes[*first], |route, &id| &route.children[id])
+    }
+}
+
+fn router() -> RouterContext {
+    try_context().expect("not invoked within a router")
+}
+
+/// The nesting depth of the current route.
+#[derive(Clone, Copy)]
+struct Depth(usize);
+
+#[derive(Clone)]
+struct RequestUrl(String);
+
+/// A matched route, along with the parameters captured by it and its parents.
+#[derive(Clone, PartialEq)]
+struct Matched {
+    route: Vec<usize>,
+    params: Params,
+}
+
+/// Finds the chain of routes matching `path`, from the outermost route inwards.
+fn match_routes(routes: &[Route], path: &[String]) -> Option<Vec<Matched>> {
+    routes.iter().enumerate().find_map(|(id, route)| {
+        let (params, consumed) = route.pattern.match_prefix(path)?;
+        let rest = &path[consumed..];
+
+        if let Some(mut chain) = match_routes(&route.children, rest) {
+            for matched in &mut chain {
+                matched.route.insert(0, id);
+                let mut inherited = params.clone();
+                inherited.extend(matched.params.clone());
+                matched.params = inherited;
+            }
+            chain.insert(
+                0,
+                Matched {
+                    route: vec![id],
+                    params,
+                },
+            );
+            Some(chain)
+        } else if rest.is_empty() {
+            Some(vec![Matched {
+                route: vec![id],
+                params,
+            }])
+        } else {
+            None
+        }
+    })
+}
+
+/// Returns `true` if the router runs against the browser's window, rather than on the server or in a
+/// [virtual](crate::dom::virt) DOM.
+fn has_window() -> bool {
+    (is_browser() || is_hydrating()) && !dom::is_virtual()
+}
+
+fn browser_location() -> Location {
+    let location = window().location();
+    Location::parse(&format!(
+        "{}{}{}",
+        location.pathname().unwrap(),
+        location.search().unwrap(),
+        location.hash().unwrap()
+    ))
+}
+
+/// Updates the router's location when the user navigates through the session history.
+struct PopStateListener(Closure<dyn FnMut(web_sys::Event)>);
+
+impl PopStateListener {
+    fn new(location: Signal<Location>) -> Self {
+        let closure = Closure::new(move |_| location.set(browser_location()));
+        window()
+            .add_event_listener_with_callback("popstate", closure.as_ref().unchecked_ref())
+            .unwrap();
+        Self(closure)
+    }
+}
+
+impl Drop for PopStateListener {
+    fn drop(&mut self) {
+        window()
+            .remove_event_listener_with_callback("popstate", self.0.as_ref().unchecked_ref())
+            .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        browser::mount,
+        dom::virt::{Document, Event},
+        render::{render_to_string, Mode},
+    };
+
+    fn segments(path: &str) -> Vec<String> {
+        Location::parse(path).segments()
+    }
+
+    #[test]
+    fn patterns() {
+        let (params, consumed) = Pattern::new("/users/:id")
+            .match_prefix(&segments("/users/4/posts"))
+            .unwrap();
+        assert_eq!(params.parse::<u32>("id"), Ok(4));
+        assert_eq!(consumed, 2);
+
+        let (params, _) = Pattern::new("/files/*path")
+            .match_prefix(&segments("/files/a%20b/c"))
+            .unwrap();
+        assert_eq!(params.get("path"), Some("a b/c"));
+
+        assert!(Pattern::new("/users/:id")
+            .match_prefix(&segments("/users"))
+            .is_none());
+    }
+
+    #[test]
+    fn query_strings() {
+        let location = Location::parse("https://example.com/search?q=a+b&tag=x&tag=y#results");
+        assert_eq!(location.path, "/search");
+        assert_eq!(location.query.get("q"), Some("a b"));
+        assert_eq!(
+            location.query.get_all("tag").collect::<Vec<_>>(),
+            ["x", "y"]
+        );
+        assert_eq!(location.hash, "results");
+        assert_eq!(location.to_string(), "/search?q=a%20b&tag=x&tag=y#results");
+    }
+
+    #[test]
+    fn relative_urls() {
+        let current = Location::parse("/users/4/posts?tab=all#top");
+        let resolve = |url: &str| current.resolve(url).to_string();
+        assert_eq!(resolve("comments"), "/users/4/comments");
+        assert_eq!(resolve("../5?tab=new"), "/users/5?tab=new");
+        assert_eq!(resolve("./"), "/users/4/");
+        assert_eq!(resolve("?tab=new"), "/users/4/posts?tab=new");
+        assert_eq!(resolve("#end"), "/users/4/posts?tab=all#end");
+        assert_eq!(resolve("/about"), "/about");
+        assert_eq!(resolve("//example.com/a"), "/a");
+
+        assert!(location::has_scheme("mailto:someone@example.com"));
+        assert!(location::has_scheme("tel:+123"));
+        assert!(!location::has_scheme("posts/a:b"));
+    }
+
+    #[test]
+    fn nested_routes_render_on_server() {
+        let app = || {
+            Router::new()
+                .route(Route::new("/", || Node::text("home".into())))
+                .route(
+                    Route::new("/users", || {
+                        [Node::text("users:".into()), outlet()]
+                            .into_iter()
+                            .collect()
+                    })
+                    .child(Route::new("", || Node::text("list".into())))
+                    .child(Route::new(":id", || {
+                        Node::text(params().get("id").unwrap().into())
+                    })),
+                )
+                .fallback(|| Node::text("not found".into()))
+                .into_node()
+        };
+        let render =
+            |url: &'static str| render_to_string(Mode::empty(), move || with_url(url, app));
+
+        assert_eq!(render("/"), "home");
+        assert_eq!(render("/users"), "users:list");
+        assert_eq!(render("/users/7?tab=posts"), "users:7");
+        assert_eq!(render("/users/7/posts"), "not found");
+    }
+
+    #[test]
+    fn routers_navigate_in_virtual_dom() {
+        let document = Document::new();
+        let _mount = mount(document.body(), || {
+            Router::new()
+                .route(Route::new("/", || link("users/4", "user")))
+                .route(Route::new("/users/:id", || {
+                    let id = params().get("id").unwrap().to_string();
+                    [Node::text(id), link("5", "next")].into_iter().collect()
+                }))
+                .into_node()
+        });
+        let click_link = || {
+            let link = document.body().first_child().unwrap();
+            let link = link.next_sibling().unwrap_or(link);
+            link.dispatch_event(&Event::new("click").bubbles(true));
+        };
+        assert_eq!(document.body().text_content(), "user");
+
+        click_link();
+        assert_eq!(document.body().text_content(), "4next");
+        click_link();
+        assert_eq!(document.body().text_content(), "5next");
+    }
+}
@@ -0,0 +1,164 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use indexmap::IndexMap;
+
+use super::location::decode;
+
+/// A route path pattern.
+///
+/// Patterns are made up of `/`-separated segments, each of which is one of:
+/// - a static segment (`users`), matching itself exactly;
+/// - a parameter (`:id`), matching any single segment;
+/// - a wildcard (`*` or `*rest`), matching the remainder of the path. Wildcards must be the final segment.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Pattern {
+    segments: Vec<Segment>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Segment {
+    Static(String),
+    Param(String),
+    Wildcard(Option<String>),
+}
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Self {
+        let segments: Vec<_> = split(pattern)
+            .map(|segment| {
+                if let Some(name) = segment.strip_prefix(':') {
+                    Segment::Param(name.to_string())
+                } else if let Some(name) = segment.strip_prefix('*') {
+                    Segment::Wildcard((!name.is_empty()).then(|| name.to_string()))
+                } else {
+                    Segment::Static(decode(segment))
+                }
+            })
+            .collect();
+
+        if let Some(i) = segments
+            .iter()
+            .position(|segment| matches!(segment, Segment::Wildcard(_)))
+        {
+            if i != segments.len() - 1 {
+                panic!("wildcard must be the final segment of route pattern `{pattern}`");
+            }
+        }
+
+        Self { segments }
+    }
+
+    /// Matches the pattern against the start of `path`, returning the captured parameters and the number of
+    /// path segments consumed.
+    pub(crate) fn match_prefix(&self, path: &[String]) -> Option<(Params, usize)> {
+        let mut params = Params::default();
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Wildcard(name) => {
+                    if let Some(name) = name {
+                        params.insert(name.clone(), path[i.min(path.len())..].join("/"));
+                    }
+                    return Some((params, path.len()));
+                }
+                Segment::Static(expected) => {
+                    if path.get(i) != Some(expected) {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => {
+                    params.insert(name.clone(), path.get(i)?.clone());
+                }
+            }
+        }
+        Some((params, self.segments.len()))
+    }
+}
+
+impl From<&str> for Pattern {
+    fn from(pattern: &str) -> Self {
+        Self::new(pattern)
+    }
+}
+
+/// Splits a path into its non-empty segments.
+pub(crate) fn split(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+/// Parameters captured from the current path by the matched routes.
+///
+/// Nested routes see the parameters of all of their parent routes.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct Params(IndexMap<String, String>);
+
+impl Params {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    pub fn parse<T: FromStr>(&self, key: &str) -> Result<T, ParamError> {
+        let value = self
+            .get(key)
+            .ok_or_else(|| ParamError::Missing(key.to_string()))?;
+        value.parse().map_err(|_| ParamError::Invalid {
+            key: key.to_string(),
+            value: value.to_string(),
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub(crate) fn insert(&mut self, key: String, value: String) {
+        self.0.insert(key, value);
+    }
+
+    pub(crate) fn extend(&mut self, other: Self) {
+        self.0.extend(other.0);
+    }
+}
+
+/// Types which can be parsed from route [`Params`].
+///
+/// ```ignore
+/// struct UserParams {
+///     id: u32,
+/// }
+///
+/// impl FromParams for UserParams {
+///     fn from_params(params: &Params) -> Result<Self, ParamError> {
+///         Ok(Self {
+///             id: params.parse("id")?,
+///         })
+///     }
+/// }
+/// ```
+pub trait FromParams: Sized {
+    fn from_params(params: &Params) -> Result<Self, ParamError>;
+}
+
+impl FromParams for Params {
+    fn from_params(params: &Params) -> Result<Self, ParamError> {
+        Ok(params.clone())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ParamError {
+    Missing(String),
+    Invalid { key: String, value: String },
+}
+
+impl fmt::Display for ParamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Missing(key) => write!(f, "missing parameter `{key}`"),
+            Self::Invalid { key, value } => {
+                write!(f, "invalid value `{value}` for parameter `{key}`")
+            }
+        }
+    }
+}
+
+impl Error for ParamError {}
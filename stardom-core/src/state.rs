@@ -0,0 +1,169 @@
+use std::{cell::RefCell, fmt, mem, thread_local};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    component::Position,
+    dom,
+    env::{is_browser, is_hydrating},
+};
+
+/// The id of the script element containing the transferred state.
+const SCRIPT_ID: &str = "stardom-state";
+
+thread_local! {
+    static TRANSFER: RefCell<Transfer> = RefCell::default();
+}
+
+/// The state transferred by a single server render or hydration.
+#[derive(Default)]
+pub(crate) struct Transfer {
+    values: Map<String, Value>,
+    root: Position,
+}
+
+/// Computes a value on the server, and reuses it when hydrating rather than computing it again.
+///
+/// During a server render, the value returned by `f` is serialized into the rendered HTML, keyed by the position
+/// of the calling component within the component tree. When hydrating, the value is read back from the HTML
+/// instead of calling `f`, as long as the client creates the same component tree. Outside of hydration, or if no
+/// value was transferred, `f` is called as usual.
+///
+/// The state is only rendered in [`Mode::HYDRATION`](crate::render::Mode::HYDRATION).
+///
+/// ```ignore
+/// #[component]
+/// fn profile(id: u32) -> Node {
+///     let user = transfer(|| load_user(id));
+///     p!(user.name)
+/// }
+/// ```
+///
+/// # Panics
+///
+/// Panics if the value cannot be serialized during a server render.
+pub fn transfer<T, F>(f: F) -> T
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> T,
+{
+    if is_browser() {
+        return f();
+    }
+
+    let key = Position::current().next_state();
+    if is_hydrating() {
+        return take(&key).unwrap_or_else(f);
+    }
+
+    let value = f();
+    store(key, &value);
+    value
+}
+
+/// Transfers `value` under `key`, for state which is only known after its component has been created.
+///
+/// # Panics
+///
+/// Panics if the value cannot be serialized.
+pub(crate) fn store<T: Serialize>(key: String, value: &T) {
+    let json = serde_json::to_value(value).expect("failed to serialize transferred state");
+    TRANSFER.with_borrow_mut(|transfer| transfer.values.insert(key, json));
+}
+
+/// Takes the value transferred under `key`, if any.
+pub(crate) fn take<T: DeserializeOwned>(key: &str) -> Option<T> {
+    let value = TRANSFER.with_borrow_mut(|transfer| transfer.values.remove(key));
+    value.and_then(|value| serde_json::from_value(value).ok())
+}
+
+/// Returns the position which components created outside of any other component descend from.
+pub(crate) fn root() -> Position {
+    TRANSFER.with_borrow(|transfer| transfer.root.clone())
+}
+
+pub(crate) fn replace(transfer: Transfer) -> Transfer {
+    TRANSFER.replace(transfer)
+}
+
+/// Takes the state rendered by the server out of the current document.
+///
+/// Streamed renders write a script with the state of each chunk, which are merged in document order.
+pub(crate) fn load() -> Transfer {
+    let mut values = Map::new();
+    while let Some(script) = dom::document().get_element_by_id(SCRIPT_ID) {
+        if let Some(parent) = script.parent_node() {
+            parent.remove_child(&script);
+        }
+        if let Ok(Value::Object(chunk)) = serde_json::from_str(&script.text_content()) {
+            values.extend(chunk);
+        }
+    }
+
+    Transfer {
+        values,
+        root: Position::default(),
+    }
+}
+
+/// Writes the script containing the state transferred since the last script was written, if any.
+pub(crate) fn write_script<W: fmt::Write>(w: &mut W) -> fmt::Result {
+    let values = TRANSFER.with_borrow_mut(|transfer| mem::take(&mut transfer.values));
+    if values.is_empty() {
+        return Ok(());
+    }
+
+    // `<` only occurs within strings, where it is escaped so that the script cannot be closed early
+    let json = Value::Object(values).to_string().replace('<', "\\u003c");
+    write!(
+        w,
+        "<script type=\"application/json\" id=\"{SCRIPT_ID}\">{json}</script>"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::{
+        dom::virt::Document,
+        hydrate::hydrate,
+        render::{render_to_string, Mode},
+        Node,
+    };
+
+    thread_local! {
+        static CALLS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    fn app() -> Node {
+        let item = |label: &'static str| {
+            Node::component(move || {
+                let text = transfer(|| {
+                    CALLS.set(CALLS.get() + 1);
+                    format!("</script>{label}")
+                });
+                Node::text(text)
+            })
+        };
+        Node::component(|| [item("a"), item("b")].into_iter().collect())
+    }
+
+    #[test]
+    fn state_is_transferred_by_position() {
+        let html = render_to_string(Mode::HYDRATION, app);
+        assert_eq!(CALLS.get(), 2);
+        assert!(html.ends_with(
+            "<script type=\"application/json\" id=\"stardom-state\">\
+             {\".0.0:0\":\"\\u003c/script>a\",\".0.1:0\":\"\\u003c/script>b\"}</script>"
+        ));
+
+        let document = Document::new();
+        document.body().set_inner_html(&html);
+        let _mount = hydrate(document.body(), app);
+        assert_eq!(CALLS.get(), 2);
+        assert_eq!(document.body().text_content(), "</script>a</script>b");
+    }
+}
@@ -0,0 +1,448 @@
+use std::{
+    cell::{Cell, OnceCell, RefCell},
+    future::{poll_fn, Future},
+    mem,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+    thread::LocalKey,
+    thread_local,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+use stardom_reactive::{effect, signal, untrack, Input, Output, Root, Signal, Track};
+
+use crate::{
+    component::{register_context, try_context, Position},
+    dom,
+    env::{self, is_browser, is_hydrating, Env},
+    flow::switch,
+    node::{Node, NodeKind},
+    state::{self, Transfer},
+};
+
+type Task = Pin<Box<dyn Future<Output = ()>>>;
+
+thread_local! {
+    static TASKS: RefCell<Vec<Task>> = RefCell::default();
+    /// Tasks spawned while mounted into a virtual DOM, which has no event loop to drive them.
+    static VIRTUAL_TASKS: RefCell<Vec<Task>> = RefCell::default();
+    static POLLING_VIRTUAL: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The boundary that resources created beneath a [`suspense`] node report their pending reads to.
+#[derive(Clone, Copy)]
+struct Boundary {
+    pending: Signal<usize>,
+}
+
+impl Boundary {
+    fn update(&self, f: impl FnOnce(&mut usize)) {
+        if !self.pending.is_disposed() {
+            self.pending.update(f);
+        }
+    }
+}
+
+/// Asynchronously loaded data.
+///
+/// Reading a resource returns `None` until its data has loaded.
+pub struct Resource<T: 'static> {
+    value: Signal<Option<T>>,
+    loading: Signal<bool>,
+}
+
+impl<T> Resource<T> {
+    /// Returns `true` while the data is being fetched.
+    pub fn loading(&self) -> bool {
+        self.loading.get()
+    }
+}
+
+impl<T> Track for Resource<T> {
+    fn track(&self) {
+        self.value.track();
+    }
+}
+
+impl<T> Input<Option<T>> for Resource<T> {
+    fn with<U, F>(&self, f: F) -> U
+    where
+        F: FnOnce(&Option<T>) -> U,
+    {
+        self.value.with(f)
+    }
+}
+
+impl<T> Copy for Resource<T> {}
+impl<T> Clone for Resource<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+/// Creates a resource which is loaded by the future returned from `fetcher`.
+///
+/// `fetcher` is called within an effect, so the resource is fetched again whenever a signal read by `fetcher`
+/// changes; results of outdated fetches are discarded. While a fetch is pending, the nearest [`suspense`] node
+/// the resource was created beneath shows its fallback.
+///
+/// Values loaded during a server render are [transferred](crate::state::transfer) to the browser, where the
+/// first fetch is skipped when hydrating, so that resolved suspense sections can be hydrated as rendered.
+/// `fetcher` is not called at all in that case, so that work it starts eagerly is not repeated in the browser.
+/// As a consequence, such a resource reads no signals, and is not fetched again when they change.
+///
+/// ```ignore
+/// let user = resource(move || fetch_user(id.get()));
+/// ```
+pub fn resource<T, Fut, F>(mut fetcher: F) -> Resource<T>
+where
+    T: Serialize + DeserializeOwned + 'static,
+    Fut: Future<Output = T> + 'static,
+    F: FnMut() -> Fut + 'static,
+{
+    let resource = Resource {
+        value: signal(None),
+        loading: signal(false),
+    };
+    let boundary = try_context::<Boundary>();
+    let version = Rc::new(Cell::new(0u64));
+    let mut key = (!is_browser()).then(|| Position::current().next_state());
+
+    effect(move || {
+        // only the first fetch corresponds to the server's, and a transferred value means no work is started
+        let key = key.take();
+        if is_hydrating() {
+            if let Some(value) = key.as_deref().and_then(state::take) {
+                untrack(|| resource.value.set(Some(value)));
+                return;
+            }
+        }
+
+        let future = fetcher();
+        let current = version.get() + 1;
+        version.set(current);
+
+        untrack(|| {
+            resource.loading.set(true);
+            if let Some(boundary) = boundary {
+                boundary.update(|pending| *pending += 1);
+            }
+        });
+
+        let version = version.clone();
+        spawn(async move {
+            let value = future.await;
+            if let Some(key) = key.filter(|_| !is_browser() && !is_hydrating()) {
+                state::store(key, &value);
+            }
+            if version.get() == current && !resource.value.is_disposed() {
+                resource.value.set(Some(value));
+                resource.loading.set(false);
+            }
+            if let Some(boundary) = boundary {
+                boundary.update(|pending| *pending -= 1);
+            }
+        });
+    });
+
+    resource
+}
+
+/// Shows `fallback` while resources created within `children` are loading.
+///
+/// In the browser, `children` is swapped in once all of its pending reads have resolved. Synchronous server
+/// renders emit the fallback in its place, whereas [`render_async`](crate::render::render_async) waits for the
+/// reads to resolve. When hydrating, a section which the server rendered after its reads resolved is hydrated
+/// as long as the [transferred](resource) values leave nothing pending, and is replaced by content created in
+/// the browser otherwise.
+pub fn suspense<E, F>(fallback: E, children: F) -> Node
+where
+    E: FnOnce() -> Node + 'static,
+    F: FnOnce() -> Node + 'static,
+{
+    let pending = signal(0usize);
+    let content = Node::component(|| {
+        register_context(Boundary { pending });
+        children()
+    });
+
+    // the fallback is only created once shown, as it is never hydrated
+    let fallback = RefCell::new(Some(fallback));
+    let created = OnceCell::new();
+    let shown = switch(move || pending.get() > 0, {
+        let content = content.clone();
+        move |&suspended: &bool| {
+            if !suspended {
+                return content.clone();
+            }
+            created.get_or_init(|| (fallback.take().unwrap())()).clone()
+        }
+    });
+
+    let node = Node::create(NodeKind::Suspense { pending, content });
+    node.insert(&shown, None);
+    node
+}
+
+/// Runs `future` to completion in the background.
+///
+/// Outside of the browser, futures are queued on the current [`ServerRender`]. When mounted into a virtual DOM,
+/// they are polled right away, and then by [`poll_virtual_tasks`].
+pub(crate) fn spawn<F>(future: F)
+where
+    F: Future<Output = ()> + 'static,
+{
+    if !is_browser() && !is_hydrating() {
+        TASKS.with_borrow_mut(|tasks| tasks.push(Box::pin(future)));
+    } else if dom::is_virtual() {
+        VIRTUAL_TASKS.with_borrow_mut(|tasks| tasks.push(Box::pin(future)));
+        poll_virtual_tasks();
+    } else {
+        wasm_bindgen_futures::spawn_local(future);
+    }
+}
+
+/// Polls the futures of resources mounted into a [virtual](crate::dom::virt) DOM, returning `true` once all of
+/// them have finished.
+///
+/// A virtual DOM has no event loop, so futures which are not ready when spawned are only polled again when this
+/// is called.
+pub fn poll_virtual_tasks() -> bool {
+    // tasks spawned while polling are picked up by the running poll
+    if POLLING_VIRTUAL.replace(true) {
+        return false;
+    }
+    let poll = poll_queue(&VIRTUAL_TASKS, &mut Context::from_waker(Waker::noop()));
+    POLLING_VIRTUAL.set(false);
+    poll.is_ready()
+}
+
+/// The reactive runtime, pending tasks and transferred state of a server render, which may be suspended across
+/// `await` points.
+pub(crate) struct ServerRender {
+    root: Root,
+    tasks: Vec<Task>,
+    transfer: Transfer,
+}
+
+impl ServerRender {
+    pub(crate) fn new() -> Self {
+        Self {
+            root: Root::new(),
+            tasks: vec![],
+            transfer: Transfer::default(),
+        }
+    }
+
+    pub(crate) fn enter<T, F>(&mut self, f: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        let tasks = &mut self.tasks;
+        let transfer = &mut self.transfer;
+        env::with(Env::Render, || {
+            self.root.enter(|| {
+                let prev_tasks = TASKS.replace(mem::take(tasks));
+                let prev_transfer = state::replace(mem::take(transfer));
+                let value = f();
+                *tasks = TASKS.replace(prev_tasks);
+                *transfer = state::replace(prev_transfer);
+                value
+            })
+        })
+    }
+
+    /// Polls all pending tasks, including those spawned by other tasks, returning `Ready` once none are left.
+    pub(crate) fn poll(&mut self, cx: &mut Context) -> Poll<()> {
+        self.enter(|| poll_queue(&TASKS, cx))
+    }
+
+    /// Waits for all pending tasks to finish.
+    pub(crate) async fn resolve(&mut self) {
+        poll_fn(|cx| self.poll(cx)).await
+    }
+}
+
+fn poll_queue(queue: &'static LocalKey<RefCell<Vec<Task>>>, cx: &mut Context) -> Poll<()> {
+    loop {
+        let mut pending = queue.take();
+        pending.retain_mut(|task| task.as_mut().poll(cx).is_pending());
+
+        let spawned = queue.take();
+        let done = spawned.is_empty();
+        pending.extend(spawned);
+        queue.set(pending);
+
+        if done {
+            break;
+        }
+    }
+
+    if queue.with_borrow(Vec::is_empty) {
+        Poll::Ready(())
+    } else {
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::ready,
+        pin::pin,
+        task::{Context, Poll, Waker},
+    };
+
+    use futures::Stream;
+
+    use super::*;
+    use crate::{
+        browser::mount,
+        dom::virt::Document,
+        hydrate::{hydrate_with, HydrateOptions},
+        render::{render_stream, render_to_string, render_to_string_async, Mode},
+    };
+
+    fn app() -> Node {
+        suspense(
+            || Node::text("loading".into()),
+            || {
+                let data = resource(|| ready("loaded".to_string()));
+                let text = Node::text(String::new());
+                effect({
+                    let text = text.clone();
+                    move || text.set_text(data.cloned().unwrap_or_default())
+                });
+                text
+            },
+        )
+    }
+
+    #[test]
+    fn sync_render_emits_fallback() {
+        assert_eq!(
+            render_to_string(Mode::HYDRATION, app),
+            "<!--stardom:suspense-->loading<!--/stardom:suspense-->"
+        );
+    }
+
+    #[test]
+    fn async_render_waits_for_resources() {
+        let mut render = pin!(render_to_string_async(Mode::HYDRATION, app));
+        let Poll::Ready(html) = render
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()))
+        else {
+            panic!("render did not resolve");
+        };
+        assert_eq!(
+            html,
+            "<!--stardom:suspense--><!--stardom:resolved--><!--stardom:fragment-->\
+             <!--stardom:component-->loaded<!--/stardom:component--><!--/stardom:fragment-->\
+             <!--/stardom:suspense--><script type=\"application/json\" id=\"stardom-state\">\
+             {\".0:0\":\"loaded\"}</script>"
+        );
+    }
+
+    #[test]
+    fn resolved_sections_are_hydrated() {
+        thread_local! {
+            static FETCHES: Cell<usize> = const { Cell::new(0) };
+        }
+        let app = || {
+            suspense(
+                || Node::text("loading".into()),
+                || {
+                    // counted when the fetch starts, like an eager request would be
+                    let data = resource(|| {
+                        FETCHES.set(FETCHES.get() + 1);
+                        async { "loaded".to_string() }
+                    });
+                    let text = Node::text(String::new());
+                    effect({
+                        let text = text.clone();
+                        move || text.set_text(data.cloned().unwrap_or_default())
+                    });
+                    text
+                },
+            )
+        };
+
+        let mut render = pin!(render_to_string_async(Mode::HYDRATION, app));
+        let Poll::Ready(html) = render
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()))
+        else {
+            panic!("render did not resolve");
+        };
+        assert_eq!(FETCHES.get(), 1);
+
+        let document = Document::new();
+        document.body().set_inner_html(&html);
+        let mut text = document.body().first_child();
+        while let Some(node) = text.as_ref().filter(|node| !node.is_text()) {
+            text = node.next_sibling();
+        }
+
+        let (_mount, errors) =
+            hydrate_with(document.body(), HydrateOptions::default(), app).unwrap();
+        assert!(errors.is_empty());
+        // the transferred value is used rather than fetching it again, and the server's text is kept
+        assert_eq!(FETCHES.get(), 1);
+        assert_eq!(document.body().first_child(), text);
+        assert_eq!(document.body().text_content(), "loaded");
+    }
+
+    #[test]
+    fn virtual_tasks_are_polled() {
+        thread_local! {
+            static READY: Cell<bool> = const { Cell::new(false) };
+        }
+        let document = Document::new();
+        let _mount = mount(document.body(), || {
+            suspense(
+                || Node::text("loading".into()),
+                || {
+                    let data = resource(|| {
+                        poll_fn(|_| match READY.get() {
+                            true => Poll::Ready("loaded".to_string()),
+                            false => Poll::Pending,
+                        })
+                    });
+                    let text = Node::text(String::new());
+                    effect({
+                        let text = text.clone();
+                        move || text.set_text(data.cloned().unwrap_or_default())
+                    });
+                    text
+                },
+            )
+        });
+
+        assert!(!poll_virtual_tasks());
+        assert_eq!(document.body().text_content(), "loading");
+        READY.set(true);
+        assert!(poll_virtual_tasks());
+        assert_eq!(document.body().text_content(), "loaded");
+    }
+
+    #[test]
+    fn stream_swaps_in_resolved_chunks() {
+        let mut stream = render_stream(Mode::empty(), app);
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut chunks = vec![];
+        while let Poll::Ready(Some(chunk)) = Pin::new(&mut stream).poll_next(&mut cx) {
+            chunks.push(String::from_utf8(chunk.to_vec()).unwrap());
+        }
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(
+            chunks[0],
+            "<!--stardom:suspense--><template id=\"stardom-s0\"></template>loading<!--/stardom:suspense-->"
+        );
+        assert!(chunks[1].ends_with(
+            "<template id=\"stardom-c0\">loaded</template><script>$stardomSwap(0)</script>"
+        ));
+    }
+}
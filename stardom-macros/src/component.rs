@@ -3,17 +3,27 @@ use quote::quote;
 use syn::{
     parse::{Parse, ParseStream},
     spanned::Spanned,
-    ItemFn,
+    Ident, ItemFn, ReturnType, Type,
 };
 
 use crate::util::*;
 
-// TODO
-pub struct ComponentArgs {}
+pub struct ComponentArgs {
+    /// Set by `#[component(fallible)]`, for components returning an alias of `Result` such as `io::Result`.
+    fallible: bool,
+}
 
 impl Parse for ComponentArgs {
-    fn parse(_input: ParseStream) -> syn::Result<Self> {
-        Ok(Self {})
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.is_empty() {
+            return Ok(Self { fallible: false });
+        }
+
+        let ident = input.parse::<Ident>()?;
+        if ident != "fallible" {
+            return Err(syn::Error::new(ident.span(), "unrecognized argument"));
+        }
+        Ok(Self { fallible: true })
     }
 }
 
@@ -30,11 +40,27 @@ impl Parse for Component {
 }
 
 impl Component {
-    pub fn to_tokens(&self, _args: ComponentArgs, tokens: &mut TokenStream) {
-        let Paths { Node, .. } = paths();
+    pub fn to_tokens(&self, args: ComponentArgs, tokens: &mut TokenStream) {
+        let Paths { Node, error, .. } = paths();
 
         let mut item_fn = self.item_fn.clone();
         let block = &item_fn.block;
+
+        // components returning a `Result` report errors to the nearest error boundary
+        if let ReturnType::Type(_, ty) = &item_fn.sig.output {
+            if args.fallible || is_result(ty) {
+                item_fn.block = syn::parse_quote_spanned! {block.span()=> {
+                    #error::try_component(|| -> #ty #block)
+                }};
+                item_fn.sig.output = syn::parse_quote!(-> #Node);
+
+                tokens.extend(quote! {
+                    #item_fn
+                });
+                return;
+            }
+        }
+
         item_fn.block = syn::parse_quote_spanned! {block.span()=> {
             #Node::component(|| #block)
         }};
@@ -50,3 +76,28 @@ impl Component {
         tokens
     }
 }
+
+/// Returns whether `ty` is spelled as the standard library's `Result`. Aliases cannot be recognized, as the
+/// macro only sees the type's path.
+fn is_result(ty: &Type) -> bool {
+    let Type::Path(path) = ty else {
+        return false;
+    };
+    if path.qself.is_some() {
+        return false;
+    }
+
+    let segments = path
+        .path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect::<Vec<_>>();
+    match segments.as_slice() {
+        [result] => result == "Result" && path.path.leading_colon.is_none(),
+        [krate, module, result] => {
+            matches!(krate.as_str(), "std" | "core") && module == "result" && result == "Result"
+        }
+        _ => false,
+    }
+}
@@ -118,6 +118,22 @@ const ELEMENTS: &[&str] = &[
    "template",
 ];
 
+const VOID: &[&str] = &[
+   "area",
+   "base",
+   "br",
+   "col",
+   "embed",
+   "hr",
+   "img",
+   "input",
+   "link",
+   "meta",
+   "source",
+   "track",
+   "wbr",
+];
+
 const ATTRS: &[&str] = &[
    "accesskey",
    "autocapitalize",
@@ -0,0 +1,56 @@
+use convert_case::{Case, Casing};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, LitStr};
+
+use crate::util::*;
+
+/// Implements `IntoAttr` for an enum of unit variants, mapping each to its name in kebab case, or to the string
+/// given by `#[attr("...")]`.
+pub fn derive(input: DeriveInput) -> syn::Result<TokenStream> {
+    let Paths { IntoAttr, .. } = paths();
+
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "`IntoAttr` cannot be derived for generic enums",
+        ));
+    }
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "`IntoAttr` can only be derived for enums",
+        ));
+    };
+
+    let mut arms = vec![];
+    for variant in &data.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                &variant.fields,
+                "`IntoAttr` can only be derived for unit variants",
+            ));
+        }
+
+        let mut value = variant.ident.to_string().to_case(Case::Kebab);
+        for attr in &variant.attrs {
+            if attr.path().is_ident("attr") {
+                value = attr.parse_args::<LitStr>()?.value();
+            }
+        }
+
+        let ident = &variant.ident;
+        arms.push(quote!(Self::#ident => #value));
+    }
+
+    let ident = &input.ident;
+    Ok(quote! {
+        impl<'a> #IntoAttr<'a> for #ident {
+            fn into_attr(self) -> ::std::option::Option<::std::borrow::Cow<'a, ::std::primitive::str>> {
+                ::std::option::Option::Some(::std::borrow::Cow::Borrowed(match self {
+                    #(#arms,)*
+                }))
+            }
+        }
+    })
+}
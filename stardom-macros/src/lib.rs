@@ -4,6 +4,7 @@ mod bindings;
 mod component;
 mod element;
 mod fragment;
+mod into_attr;
 mod named;
 mod stmt;
 mod util;
@@ -61,6 +62,16 @@ pub fn bind_value(input: TokenStream) -> TokenStream {
     bindings::bind_value(bind).into()
 }
 
+// Attributes
+
+#[proc_macro_derive(IntoAttr, attributes(attr))]
+pub fn derive_into_attr(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+    into_attr::derive(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
 // Include definitions from stardom-codegen
 
 macro_rules! define_elements {
@@ -10,12 +10,14 @@ use crate::util::*;
 
 mod kw {
     syn::custom_keyword!(elements);
+    syn::custom_keyword!(void);
     syn::custom_keyword!(attributes);
     syn::custom_keyword!(events);
 }
 
 pub enum Named {
     Elements,
+    Void,
     Attributes,
     Events,
 }
@@ -26,6 +28,9 @@ impl Parse for Named {
         if lookahead.peek(kw::elements) {
             input.parse::<kw::elements>()?;
             Ok(Self::Elements)
+        } else if lookahead.peek(kw::void) {
+            input.parse::<kw::void>()?;
+            Ok(Self::Void)
         } else if lookahead.peek(kw::attributes) {
             input.parse::<kw::attributes>()?;
             Ok(Self::Attributes)
@@ -58,6 +63,12 @@ impl ToTokens for Named {
                     pub use stardom_macros::{#macros};
                 }
             }
+            Self::Void => {
+                let void = crate::VOID;
+                quote! {
+                    pub(crate) const VOID: &[&str] = &[#(#void),*];
+                }
+            }
             Self::Attributes => crate::ATTRS
                 .iter()
                 .map(|attr| {
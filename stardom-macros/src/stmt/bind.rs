@@ -5,7 +5,6 @@ use syn::{parse::ParseStream, spanned::Spanned, Attribute, ExprMacro, Ident, Tok
 use super::{kw, StmtParse, StmtTokens};
 
 pub struct StmtBind {
-    pub bind: kw::bind,
     pub expr: ExprMacro,
 }
 
@@ -18,11 +17,11 @@ impl StmtParse for StmtBind {
             ));
         }
 
-        let bind = input.parse()?;
+        input.parse::<kw::bind>()?;
         input.parse::<Token![:]>()?;
         let expr = input.parse()?;
 
-        Ok(Self { bind, expr })
+        Ok(Self { expr })
     }
 }
 
@@ -0,0 +1,107 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::{
+    ext::IdentExt, parse::ParseStream, spanned::Spanned, Attribute, Expr, Ident, LitStr, Token,
+};
+
+use super::{kw, StmtParse, StmtTokens};
+use crate::util::*;
+
+pub enum DirectiveKind {
+    Class,
+    Style,
+    Prop,
+}
+
+/// A statement setting a single class, style property or DOM property of its element, such as
+/// `class:active => cond`.
+pub struct StmtDirective {
+    pub kind: DirectiveKind,
+    pub name: LitStr,
+    pub value: Expr,
+}
+
+impl StmtParse for StmtDirective {
+    fn parse_with_attrs(attrs: Vec<Attribute>, input: ParseStream) -> syn::Result<Self> {
+        if !attrs.is_empty() {
+            return Err(syn::Error::new(
+                attrs[0].span(),
+                "directives do not support attributes",
+            ));
+        }
+
+        let kind = if input.peek(kw::class) {
+            input.parse::<kw::class>()?;
+            DirectiveKind::Class
+        } else if input.peek(kw::style) {
+            input.parse::<kw::style>()?;
+            DirectiveKind::Style
+        } else {
+            input.parse::<kw::prop>()?;
+            DirectiveKind::Prop
+        };
+        input.parse::<Token![:]>()?;
+        let name = parse_name(input)?;
+        input.parse::<Token![=>]>()?;
+        let value = input.parse()?;
+
+        Ok(Self { kind, name, value })
+    }
+}
+
+/// Parses a name which is either a string literal, or identifiers joined by hyphens such as `background-color`.
+fn parse_name(input: ParseStream) -> syn::Result<LitStr> {
+    if input.peek(LitStr) {
+        return input.parse();
+    }
+
+    let first = Ident::parse_any(input)?;
+    let mut name = first.to_string();
+    while input.peek(Token![-]) {
+        input.parse::<Token![-]>()?;
+        name.push('-');
+        name.push_str(&Ident::parse_any(input)?.to_string());
+    }
+    Ok(LitStr::new(&name, first.span()))
+}
+
+impl StmtTokens for StmtDirective {
+    fn apply(&self, target: &Ident, tokens: &mut TokenStream) {
+        let Paths {
+            reactive, IntoAttr, ..
+        } = paths();
+
+        let Self { kind, name, value } = self;
+
+        let value_span = value.span().resolved_at(value.span());
+        let set = match kind {
+            // like boolean attributes, the class is present whenever the value would set the attribute
+            DirectiveKind::Class => quote_spanned! {value_span=>
+                #target.set_class(#name, #IntoAttr::into_attr(#value).is_some())
+            },
+            DirectiveKind::Style => quote_spanned! {value_span=>
+                #target.set_style(
+                    #name,
+                    #IntoAttr::into_attr(#value).map(::std::borrow::Cow::into_owned),
+                )
+            },
+            DirectiveKind::Prop => quote_spanned! {value_span=>
+                #target.set_prop(#name, #value)
+            },
+        };
+
+        // There's no need for an effect if the value is a literal
+        if matches!(value, Expr::Lit(_)) {
+            tokens.extend(quote! {
+                #set;
+            });
+        } else {
+            tokens.extend(quote! {
+                #reactive::effect({
+                    let #target = #target.clone();
+                    move || #set
+                });
+            });
+        }
+    }
+}
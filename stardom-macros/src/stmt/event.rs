@@ -36,6 +36,10 @@ impl StmtTokens for StmtEvent {
         let mut once = None;
         let mut capture = None;
         let mut passive = None;
+        let mut prevent_default = None;
+        let mut stop_propagation = None;
+        let mut self_only = None;
+        let mut key_filter = None;
 
         for attr in attrs {
             let ident = attr.path().get_ident().map(|ident| ident.to_string());
@@ -53,6 +57,22 @@ impl StmtTokens for StmtEvent {
                     Ok(expr) => passive = Some(expr),
                     Err(err) => tokens.extend(err.to_compile_error()),
                 },
+                Some("prevent_default") => match flag(attr) {
+                    Ok(expr) => prevent_default = Some(expr),
+                    Err(err) => tokens.extend(err.to_compile_error()),
+                },
+                Some("stop_propagation") => match flag(attr) {
+                    Ok(expr) => stop_propagation = Some(expr),
+                    Err(err) => tokens.extend(err.to_compile_error()),
+                },
+                Some("self_only") => match flag(attr) {
+                    Ok(expr) => self_only = Some(expr),
+                    Err(err) => tokens.extend(err.to_compile_error()),
+                },
+                Some("key") => match attr.parse_args::<Expr>() {
+                    Ok(expr) => key_filter = Some(expr),
+                    Err(err) => tokens.extend(err.to_compile_error()),
+                },
                 _ => tokens.extend(quote_spanned! {attr.span()=>
                     compile_error!("unrecognized attribute");
                 }),
@@ -75,6 +95,10 @@ impl StmtTokens for StmtEvent {
         let once = once.map(|v| quote!(.once(#v)));
         let capture = capture.map(|v| quote!(.capture(#v)));
         let passive = passive.map(|v| quote!(.passive(#v)));
+        let prevent_default = prevent_default.map(|v| quote!(.prevent_default(#v)));
+        let stop_propagation = stop_propagation.map(|v| quote!(.stop_propagation(#v)));
+        let self_only = self_only.map(|v| quote!(.self_only(#v)));
+        let key_filter = key_filter.map(|v| quote!(.key(#v)));
 
         let key_span = key.span().resolved_at(key.span());
         let key = quote_spanned! {key_span=> {
@@ -89,9 +113,13 @@ impl StmtTokens for StmtEvent {
                 #EventOptions::new()
                     #once
                     #capture
-                    #passive,
+                    #passive
+                    #prevent_default
+                    #stop_propagation
+                    #self_only
+                    #key_filter,
                 #f
-            );
+            ).forget();
         });
     }
 }
@@ -1,5 +1,6 @@
 mod attr;
 mod bind;
+mod directive;
 mod event;
 mod local;
 mod node;
@@ -12,13 +13,16 @@ use syn::{
 };
 
 pub use self::{
-    attr::StmtAttr, bind::StmtBind, event::StmtEvent, local::StmtLocal, node::StmtNode,
-    reactive::StmtReactive,
+    attr::StmtAttr, bind::StmtBind, directive::StmtDirective, event::StmtEvent, local::StmtLocal,
+    node::StmtNode, reactive::StmtReactive,
 };
 
 mod kw {
     syn::custom_keyword!(on);
     syn::custom_keyword!(bind);
+    syn::custom_keyword!(class);
+    syn::custom_keyword!(style);
+    syn::custom_keyword!(prop);
 }
 
 trait StmtParse: Sized {
@@ -40,6 +44,7 @@ pub enum Stmt {
     Node(StmtNode),
     Reactive(StmtReactive),
     Attr(StmtAttr),
+    Directive(StmtDirective),
     Event(StmtEvent),
     Bind(StmtBind),
 }
@@ -75,7 +80,11 @@ impl Stmt {
 
     fn needs_separator(&self) -> bool {
         match self {
-            Self::Local(_) | Self::Attr(_) | Self::Event(_) | Self::Bind(_) => true,
+            Self::Local(_)
+            | Self::Attr(_)
+            | Self::Directive(_)
+            | Self::Event(_)
+            | Self::Bind(_) => true,
             Self::Node(node) => node.needs_separator(),
             Self::Reactive(reactive) => reactive.stmt.needs_separator(),
         }
@@ -94,6 +103,10 @@ impl Parse for Stmt {
             StmtParse::parse_with_attrs(attrs, input).map(Self::Event)
         } else if input.peek(kw::bind) && input.peek2(Token![:]) {
             StmtBind::parse_with_attrs(attrs, input).map(Self::Bind)
+        } else if (input.peek(kw::class) || input.peek(kw::style) || input.peek(kw::prop))
+            && input.peek2(Token![:])
+        {
+            StmtParse::parse_with_attrs(attrs, input).map(Self::Directive)
         } else if input.peek2(Token![=>]) {
             StmtAttr::parse_with_attrs(attrs, input).map(Self::Attr)
         } else {
@@ -109,6 +122,7 @@ impl StmtTokens for Stmt {
             Self::Node(stmt) => stmt.apply(target, tokens),
             Self::Reactive(stmt) => stmt.apply(target, tokens),
             Self::Attr(stmt) => stmt.apply(target, tokens),
+            Self::Directive(stmt) => stmt.apply(target, tokens),
             Self::Event(stmt) => stmt.apply(target, tokens),
             Self::Bind(stmt) => stmt.apply(target, tokens),
         }
@@ -10,14 +10,13 @@ use super::{StmtNode, StmtTokens};
 use crate::util::*;
 
 pub struct StmtReactive {
-    pub colon_token: Token![:],
     pub stmt: StmtNode,
 }
 
 impl Parse for StmtReactive {
     fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<Token![:]>()?;
         Ok(Self {
-            colon_token: input.parse()?,
             stmt: input.parse()?,
         })
     }
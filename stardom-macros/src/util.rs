@@ -4,9 +4,9 @@ use syn::{parse_quote as q, Path};
 #[derive(Clone)]
 pub struct Paths {
     pub reactive: Path,
-    pub macros: Path,
     pub named: Path,
     pub bindings: Path,
+    pub error: Path,
     pub web_sys: Path,
 
     pub Node: Path,
@@ -22,9 +22,9 @@ impl Paths {
     fn new() -> Self {
         Self {
             reactive: q!(stardom_reactive),
-            macros: q!(stardom_macros),
             named: q!(stardom_core::named),
             bindings: q!(stardom_core::__macro::bindings),
+            error: q!(stardom_core::error),
             web_sys: q!(stardom_core::__macro::web_sys),
             Node: q!(stardom_core::Node),
             IntoNode: q!(stardom_core::IntoNode),
@@ -0,0 +1,161 @@
+use std::{cell::RefCell, fmt, panic::Location, thread_local};
+
+use crate::runtime::Runtime;
+
+type LeakHook = Box<dyn Fn(&LeakReport)>;
+
+thread_local! {
+    static LEAK_HOOK: RefCell<Option<LeakHook>> = RefCell::default();
+}
+
+/// The kind of a reactive item tracked by the runtime.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ItemKind {
+    Signal,
+    Effect,
+    Scope,
+}
+
+impl fmt::Display for ItemKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Signal => "signal",
+            Self::Effect => "effect",
+            Self::Scope => "scope",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Item {
+    pub kind: ItemKind,
+    pub location: &'static Location<'static>,
+}
+
+/// Counts of live reactive items.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Stats {
+    pub signals: usize,
+    pub effects: usize,
+    pub scopes: usize,
+}
+
+impl Stats {
+    pub fn total(&self) -> usize {
+        self.signals + self.effects + self.scopes
+    }
+
+    pub(crate) fn add(&mut self, kind: ItemKind) {
+        match kind {
+            ItemKind::Signal => self.signals += 1,
+            ItemKind::Effect => self.effects += 1,
+            ItemKind::Scope => self.scopes += 1,
+        }
+    }
+}
+
+/// Returns the number of live items within the current runtime.
+pub fn stats() -> Stats {
+    Runtime::with(|rt| {
+        rt.items
+            .borrow()
+            .values()
+            .fold(Stats::default(), |mut stats, item| {
+                stats.add(item.kind);
+                stats
+            })
+    })
+}
+
+/// An item which was never disposed before its runtime was dropped.
+#[derive(Clone, Copy, Debug)]
+pub struct LeakedItem {
+    pub kind: ItemKind,
+    pub location: &'static Location<'static>,
+}
+
+/// The items left undisposed when a runtime was dropped.
+///
+/// Everything owned by the runtime's root scope is disposed along with the runtime, so a leak is either a
+/// [`Scope`](crate::Scope) that outlived its runtime, or an item created within a scope after it was disposed.
+#[derive(Clone, Default, Debug)]
+pub struct LeakReport {
+    pub items: Vec<LeakedItem>,
+}
+
+impl LeakReport {
+    pub(crate) fn new(items: impl IntoIterator<Item = Item>) -> Self {
+        let mut items: Vec<_> = items
+            .into_iter()
+            .map(|Item { kind, location }| LeakedItem { kind, location })
+            .collect();
+        items.sort_by_key(|item| {
+            (
+                item.location.file(),
+                item.location.line(),
+                item.location.column(),
+            )
+        });
+        Self { items }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.items.iter().fold(Stats::default(), |mut stats, item| {
+            stats.add(item.kind);
+            stats
+        })
+    }
+
+    /// Prints the report to stderr, which is what the default [leak hook](set_leak_hook) does outside of the
+    /// browser.
+    pub fn eprint(&self) {
+        eprintln!("{self}");
+    }
+
+    pub(crate) fn emit(&self) {
+        LEAK_HOOK.with_borrow(|hook| match hook {
+            Some(hook) => hook(self),
+            None => self.log(),
+        });
+    }
+
+    #[cfg(all(feature = "web", target_arch = "wasm32"))]
+    fn log(&self) {
+        web_sys::console::warn_1(&self.to_string().into());
+    }
+
+    #[cfg(not(all(feature = "web", target_arch = "wasm32")))]
+    fn log(&self) {
+        self.eprint();
+    }
+}
+
+impl fmt::Display for LeakReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "reactive runtime dropped with {} undisposed item(s)",
+            self.items.len()
+        )?;
+        for item in &self.items {
+            write!(f, "\n  {} created at {}", item.kind, item.location)?;
+        }
+        Ok(())
+    }
+}
+
+/// Replaces the function called with the [`LeakReport`] of a runtime dropped with undisposed items.
+///
+/// By default, the report is logged as a warning to the browser's console with the `web` feature on `wasm32`,
+/// and [printed to stderr](LeakReport::eprint) otherwise. Reports are silenced with `set_leak_hook(|_| {})`, or
+/// redirected elsewhere with a hook of their own.
+pub fn set_leak_hook<F>(hook: F)
+where
+    F: Fn(&LeakReport) + 'static,
+{
+    LEAK_HOOK.set(Some(Box::new(hook)));
+}
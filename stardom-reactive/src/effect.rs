@@ -1,23 +1,39 @@
 use std::{cell::RefCell, collections::HashSet, mem, rc::Rc};
 
 use crate::{
-    runtime::{batch, Handle, Runtime},
-    scope::with_scope,
+    diagnostics::ItemKind,
+    runtime::{Handle, Runtime},
+    scheduler::Phase,
     Track,
 };
 
+#[track_caller]
 pub fn effect<F>(f: F)
 where
     F: FnMut() + 'static,
 {
-    Effect::new(Handle::scoped(), f).run();
+    Effect::new(Handle::scoped(ItemKind::Effect), Phase::Render, f).run();
 }
 
+/// Creates an effect which runs in the [`Phase::Layout`] phase, after all pending render effects.
+#[track_caller]
+pub fn layout_effect<F>(f: F)
+where
+    F: FnMut() + 'static,
+{
+    Effect::new(Handle::scoped(ItemKind::Effect), Phase::Layout, f).run();
+}
+
+#[track_caller]
 pub fn lazy_effect<F>(f: F) -> LazyEffect
 where
     F: FnMut() + 'static,
 {
-    LazyEffect(Effect::new(Handle::scoped(), f))
+    LazyEffect(Effect::new(
+        Handle::scoped(ItemKind::Effect),
+        Phase::Render,
+        f,
+    ))
 }
 
 #[derive(Clone)]
@@ -39,40 +55,72 @@ impl LazyEffect {
 
 pub(crate) struct Effect {
     handle: Handle,
+    phase: Phase,
     f: Box<RefCell<dyn FnMut()>>,
     deps: RefCell<HashSet<Handle>>,
 }
 
 impl Effect {
-    pub fn new<F>(handle: Handle, f: F) -> Rc<Self>
+    pub fn new<F>(handle: Handle, phase: Phase, f: F) -> Rc<Self>
     where
         F: FnMut() + 'static,
     {
-        Rc::new(Self {
+        let effect = Rc::new(Self {
             handle,
+            phase,
             f: Box::new(RefCell::new(f)),
             deps: RefCell::default(),
-        })
+        });
+        handle.with(|rt| {
+            rt.effects.borrow_mut().insert(handle, effect.clone());
+        });
+        effect
     }
 
     pub fn handle(&self) -> Handle {
         self.handle
     }
 
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
     pub fn add_signal(&self, handle: Handle) {
         self.deps.borrow_mut().insert(handle);
     }
 
+    /// Runs the effect, unless it has been disposed.
+    ///
+    /// Everything created during a run is owned by the effect, and disposed before the next run.
     pub fn run(self: &Rc<Self>) {
-        self.handle.with(|rt| {
+        let flush = self.handle.with(|rt| {
+            if !rt.effects.borrow().contains_key(&self.handle) {
+                return false;
+            }
+
             self.clear_deps(rt);
-            let prev = rt.current_effect.replace(Some(self.clone()));
-            with_scope(|| batch(&mut *self.f.borrow_mut()));
-            rt.current_effect.replace(prev);
+            rt.dispose_owned(self.handle);
+
+            // effects track their dependencies even when created within `untrack`
+            let guard = RunGuard {
+                rt,
+                tracking: rt.tracking.replace(true),
+                batching: rt.batching.replace(true),
+                effect: rt.current_effect.replace(Some(self.clone())),
+                scope: rt.current_scope.replace(self.handle),
+            };
+            (self.f.borrow_mut())();
+            !guard.batching
         });
+
+        // the batch is only flushed once `f` is released, so an effect which triggers itself is run again
+        // rather than re-entered
+        if flush {
+            self.handle.with(Runtime::schedule_flush);
+        }
     }
 
-    fn clear_deps(&self, rt: &Runtime) {
+    pub fn clear_deps(&self, rt: &Runtime) {
         let deps = mem::take(&mut *self.deps.borrow_mut());
         for handle in deps {
             if let Some(raw) = rt.signals.borrow().get(&handle) {
@@ -81,3 +129,21 @@ impl Effect {
         }
     }
 }
+
+/// Restores the state of the runtime once an effect has run, even if it panicked.
+struct RunGuard<'a> {
+    rt: &'a Runtime,
+    tracking: bool,
+    batching: bool,
+    effect: Option<Rc<Effect>>,
+    scope: Handle,
+}
+
+impl Drop for RunGuard<'_> {
+    fn drop(&mut self) {
+        self.rt.current_scope.set(self.scope);
+        self.rt.current_effect.replace(self.effect.take());
+        self.rt.batching.set(self.batching);
+        self.rt.tracking.set(self.tracking);
+    }
+}
@@ -1,14 +1,16 @@
 #![warn(clippy::use_self)]
 
+mod diagnostics;
 mod effect;
 mod memo;
 mod runtime;
+mod scheduler;
 mod scope;
 mod signal;
 
 use std::mem;
 
-pub use self::{effect::*, memo::*, runtime::*, scope::*, signal::*};
+pub use self::{diagnostics::*, effect::*, memo::*, runtime::*, scheduler::*, scope::*, signal::*};
 
 pub trait Track {
     fn track(&self);
@@ -56,7 +58,11 @@ pub trait Output<T> {
 mod tests {
     use super::*;
 
-    use std::{cell::Cell, rc::Rc};
+    use std::{
+        cell::{Cell, RefCell},
+        mem,
+        rc::Rc,
+    };
 
     #[test]
     fn basic_reactivity() {
@@ -89,4 +95,130 @@ mod tests {
             assert_eq!(calls.get(), 4);
         });
     }
+
+    #[test]
+    fn effect_phases() {
+        run(|_| {
+            let count = signal(0u8);
+            let log = Rc::new(RefCell::new(vec![]));
+
+            layout_effect({
+                let log = log.clone();
+                move || log.borrow_mut().push(("layout", count.get()))
+            });
+            effect({
+                let log = log.clone();
+                move || log.borrow_mut().push(("render", count.get()))
+            });
+            log.borrow_mut().clear();
+
+            count.set(1);
+            assert_eq!(*log.borrow(), [("render", 1), ("layout", 1)]);
+        });
+    }
+
+    #[test]
+    fn deferred_scheduler() {
+        #[derive(Clone, Default)]
+        struct Manual(Rc<RefCell<Vec<Flush>>>);
+
+        impl Scheduler for Manual {
+            fn schedule(&self, flush: Flush) {
+                self.0.borrow_mut().push(flush);
+            }
+        }
+
+        run(|_| {
+            let scheduler = Manual::default();
+            set_scheduler(scheduler.clone());
+
+            let count = signal(0u8);
+            let double = memo(move || count.get() * 2);
+            let calls = Rc::new(Cell::new(0));
+            effect({
+                let calls = calls.clone();
+                move || {
+                    count.track();
+                    calls.set(calls.get() + 1);
+                }
+            });
+
+            count.set(1);
+            count.set(2);
+            assert_eq!(calls.get(), 1);
+            assert_eq!(double.get(), 4);
+            assert_eq!(scheduler.0.borrow().len(), 1);
+
+            let pending = scheduler.0.borrow_mut().pop().unwrap();
+            pending.run();
+            assert_eq!(calls.get(), 2);
+
+            // a dropped flush does not keep later changes from being scheduled
+            count.set(3);
+            drop(scheduler.0.borrow_mut().pop().unwrap());
+            count.set(4);
+            scheduler.0.borrow_mut().pop().unwrap().run();
+            assert_eq!(calls.get(), 3);
+        });
+    }
+
+    #[cfg(panic = "unwind")]
+    #[test]
+    fn panicking_effects_do_not_stop_flushing() {
+        run(|_| {
+            let count = signal(0u8);
+            let seen = signal(0u8);
+            effect(move || {
+                if count.get() == 1 {
+                    panic!("effect failed");
+                }
+            });
+            effect(move || seen.set(count.get()));
+
+            let result = std::panic::catch_unwind(|| count.set(1));
+            assert!(result.is_err());
+            count.set(2);
+            assert_eq!(seen.get(), 2);
+        });
+    }
+
+    #[test]
+    fn diagnostics() {
+        let leaked = Rc::new(RefCell::new(LeakReport::default()));
+        set_leak_hook({
+            let leaked = leaked.clone();
+            move |report| *leaked.borrow_mut() = report.clone()
+        });
+
+        run(|dispose| {
+            let scope = Scope::new();
+            scope.run(|| {
+                let count = signal(0u8);
+                effect(move || {
+                    count.track();
+                    signal(());
+                });
+            });
+            let expected = Stats {
+                signals: 2,
+                effects: 1,
+                scopes: 0,
+            };
+            assert_eq!(scope.stats(), expected);
+            assert_eq!(stats().scopes, 1);
+
+            drop(scope);
+            assert_eq!(stats(), Stats::default());
+
+            let forgotten = Scope::new();
+            forgotten.run(|| signal(0u8));
+            mem::forget(forgotten);
+
+            dispose();
+        });
+
+        let report = leaked.borrow();
+        assert_eq!(report.stats().scopes, 1);
+        assert_eq!(report.items[0].location.file(), file!());
+    }
 }
@@ -1,5 +1,9 @@
-use crate::{effect::Effect, runtime::Handle, signal::Signal, Input, Output, Track};
+use crate::{
+    diagnostics::ItemKind, effect::Effect, runtime::Handle, scheduler::Phase, signal::Signal,
+    Input, Output, Track,
+};
 
+#[track_caller]
 pub fn memo<T, F>(f: F) -> Memo<T>
 where
     T: 'static,
@@ -10,27 +14,52 @@ where
 
 pub struct Memo<T: 'static> {
     signal: Signal<Option<T>>,
+    effect: Handle,
 }
 
 impl<T: 'static> Memo<T> {
+    #[track_caller]
     pub(crate) fn new<F>(mut f: F) -> Self
     where
         F: FnMut() -> T + 'static,
     {
-        let handle = Handle::scoped();
-        let signal = Signal::new(handle, None);
+        let signal = Signal::new(Handle::scoped(ItemKind::Signal), None);
 
-        Effect::new(handle, move || {
-            signal.set(Some(f()));
-        })
-        .run();
+        let effect = Effect::new(
+            Handle::scoped(ItemKind::Effect),
+            Phase::Compute,
+            move || {
+                signal.set(Some(f()));
+            },
+        );
+        effect.run();
 
-        Self { signal }
+        Self {
+            signal,
+            effect: effect.handle(),
+        }
+    }
+
+    /// Recomputes the value if a dependency changed since it was last computed, rather than waiting for the
+    /// scheduler to flush the effect queue.
+    fn refresh(&self) {
+        let queued = self.effect.with(|rt| {
+            if rt.in_batch.get() {
+                return None;
+            }
+            rt.effect_queues[Phase::Compute.index()]
+                .borrow_mut()
+                .shift_remove(&self.effect)
+        });
+        if let Some(effect) = queued {
+            effect.run();
+        }
     }
 }
 
 impl<T> Track for Memo<T> {
     fn track(&self) {
+        self.refresh();
         self.signal.track();
     }
 }
@@ -40,6 +69,7 @@ impl<T> Input<T> for Memo<T> {
     where
         F: FnOnce(&T) -> U,
     {
+        self.refresh();
         self.signal
             .with(|option| f(option.as_ref().expect("uninitialized memo")))
     }
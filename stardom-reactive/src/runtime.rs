@@ -1,15 +1,19 @@
 use std::{
     cell::{Cell, RefCell},
     collections::{HashMap, HashSet},
-    mem,
+    panic::Location,
     rc::Rc,
-    thread::AccessError,
     thread_local,
 };
 
 use indexmap::IndexMap;
 
-use crate::{effect::Effect, signal::RawSignal};
+use crate::{
+    diagnostics::{Item, ItemKind, LeakReport},
+    effect::Effect,
+    scheduler::{Flush, Phase, Scheduler, SyncScheduler},
+    signal::RawSignal,
+};
 
 thread_local! {
     static CYCLE: Cell<u64> = const { Cell::new(0) };
@@ -33,22 +37,31 @@ impl Handle {
         Runtime::with(|rt| Self::new(rt.cycle))
     }
 
-    pub fn scoped() -> Self {
+    /// Creates a handle owned by the current scope, recording the caller as its creation site.
+    #[track_caller]
+    pub fn scoped(kind: ItemKind) -> Self {
+        let location = Location::caller();
         let handle = Self::next();
-        handle.bind_scope();
+        handle.with(|rt| {
+            let scope = rt.current_scope.get();
+            rt.scopes
+                .borrow_mut()
+                .entry(scope)
+                .or_default()
+                .insert(handle);
+            rt.items
+                .borrow_mut()
+                .insert(handle, Item { kind, location });
+        });
         handle
     }
 
-    pub fn try_with<T, F>(&self, f: F) -> Result<T, AccessError>
+    /// Like [`Handle::with`], but returns `None` if the handle's runtime is no longer active.
+    pub fn try_with<T, F>(&self, f: F) -> Option<T>
     where
         F: FnOnce(&Runtime) -> T,
     {
-        Runtime::try_with(|rt| {
-            if rt.cycle != self.cycle {
-                panic!("reactive item used outside of its runtime");
-            }
-            f(rt)
-        })
+        Runtime::try_with(|rt| (rt.cycle == self.cycle).then(|| f(rt))).flatten()
     }
 
     pub fn with<T, F>(&self, f: F) -> T
@@ -63,17 +76,6 @@ impl Handle {
         })
     }
 
-    pub fn bind_scope(&self) {
-        self.with(|rt| {
-            let scope = rt.current_scope.get();
-            rt.scopes
-                .borrow_mut()
-                .entry(scope)
-                .or_default()
-                .insert(*self);
-        });
-    }
-
     pub fn set_signal(&self, raw: RawSignal) {
         self.with(|rt| {
             rt.signals.borrow_mut().insert(*self, raw);
@@ -94,37 +96,58 @@ impl Handle {
 pub(crate) struct Runtime {
     pub scopes: RefCell<HashMap<Handle, HashSet<Handle>>>,
     pub signals: RefCell<HashMap<Handle, RawSignal>>,
+    pub effects: RefCell<HashMap<Handle, Rc<Effect>>>,
+    pub items: RefCell<HashMap<Handle, Item>>,
 
     pub cycle: u64,
+    pub root: Handle,
     pub tracking: Cell<bool>,
     pub batching: Cell<bool>,
+    /// Whether within [`batch`], where memos are only recomputed once the batch has ended.
+    pub in_batch: Cell<bool>,
 
     pub current_scope: Cell<Handle>,
     pub current_effect: RefCell<Option<Rc<Effect>>>,
-    pub effect_queue: RefCell<IndexMap<Handle, Rc<Effect>>>,
+    pub effect_queues: [RefCell<IndexMap<Handle, Rc<Effect>>>; Phase::COUNT],
+
+    pub scheduler: RefCell<Rc<dyn Scheduler>>,
+    /// Shared with the scheduled [`Flush`], which clears it once run or dropped.
+    pub flush_pending: Rc<Cell<bool>>,
+    pub flushing: Cell<bool>,
 }
 
 impl Runtime {
     fn new() -> Self {
         let cycle = CYCLE.replace(CYCLE.get() + 1);
-        ID.set(0);
+        let root = Handle::new(cycle);
         Self {
             scopes: RefCell::default(),
             signals: RefCell::default(),
+            effects: RefCell::default(),
+            items: RefCell::default(),
             cycle,
+            root,
             tracking: Cell::new(true),
             batching: Cell::new(false),
-            current_scope: Cell::new(Handle::new(cycle)),
+            in_batch: Cell::new(false),
+            current_scope: Cell::new(root),
             current_effect: RefCell::default(),
-            effect_queue: RefCell::default(),
+            effect_queues: Default::default(),
+            scheduler: RefCell::new(Rc::new(SyncScheduler)),
+            flush_pending: Rc::default(),
+            flushing: Cell::new(false),
         }
     }
 
-    pub fn try_with<T, F>(f: F) -> Result<T, AccessError>
+    /// Like [`Runtime::with`], but returns `None` if there is no accessible runtime.
+    pub fn try_with<T, F>(f: F) -> Option<T>
     where
         for<'a> F: FnOnce(&'a Self) -> T,
     {
-        STACK.try_with(|cell| f(cell.borrow().last().expect("not within reactive runtime")))
+        STACK
+            .try_with(|cell| cell.try_borrow().ok()?.last().map(f))
+            .ok()
+            .flatten()
     }
 
     pub fn with<T, F>(f: F) -> T
@@ -134,24 +157,182 @@ impl Runtime {
         STACK.with_borrow(|stack| f(stack.last().expect("not within reactive runtime")))
     }
 
-    fn trigger_queued(&self) {
-        let queued = mem::take(&mut *self.effect_queue.borrow_mut());
-        for effect in queued.values() {
+    pub fn enqueue(&self, effects: IndexMap<Handle, Rc<Effect>>) {
+        for (handle, effect) in effects {
+            self.effect_queues[effect.phase().index()]
+                .borrow_mut()
+                .insert(handle, effect);
+        }
+    }
+
+    /// Asks the scheduler to flush the effect queue, unless a flush is already pending.
+    pub fn schedule_flush(&self) {
+        if self.flushing.get() || self.flush_pending.replace(true) {
+            return;
+        }
+        let scheduler = self.scheduler.borrow().clone();
+        scheduler.schedule(Flush::new(self.cycle, self.flush_pending.clone()));
+    }
+
+    pub fn flush(&self) {
+        if self.flushing.replace(true) {
+            return;
+        }
+        self.flush_pending.set(false);
+
+        // effects left queued by a panicking effect are run by the next flush
+        struct Guard<'a>(&'a Cell<bool>);
+        impl Drop for Guard<'_> {
+            fn drop(&mut self) {
+                self.0.set(false);
+            }
+        }
+        let _guard = Guard(&self.flushing);
+
+        while let Some(effect) = self.dequeue() {
             effect.run();
         }
     }
+
+    /// Disposes everything owned by `owner`, leaving `owner` itself intact.
+    pub fn dispose_owned(&self, owner: Handle) {
+        let owned = self.scopes.borrow_mut().remove(&owner);
+        if let Some(handles) = owned {
+            for handle in handles {
+                self.dispose(handle);
+            }
+        }
+    }
+
+    fn dispose(&self, handle: Handle) {
+        self.dispose_owned(handle);
+
+        self.signals.borrow_mut().remove(&handle);
+        let effect = self.effects.borrow_mut().remove(&handle);
+        if let Some(effect) = &effect {
+            effect.clear_deps(self);
+        }
+
+        // scopes stay registered until the `Scope` itself is dropped
+        let mut items = self.items.borrow_mut();
+        if !matches!(items.get(&handle), Some(item) if item.kind == ItemKind::Scope) {
+            items.remove(&handle);
+        }
+        drop(items);
+
+        // dropping an effect may drop the scopes it captured, so no borrows can be held here
+        drop(effect);
+    }
+
+    fn dequeue(&self) -> Option<Rc<Effect>> {
+        self.effect_queues.iter().find_map(|queue| {
+            queue
+                .borrow_mut()
+                .shift_remove_index(0)
+                .map(|(_, effect)| effect)
+        })
+    }
 }
 
+/// Runs `f` within a new reactive runtime.
+///
+/// `f` is given a function which disposes everything owned by the runtime, then drops it.
+/// If any items remain undisposed at that point, a [`LeakReport`] is emitted.
 pub fn run<T, F>(f: F) -> T
 where
     F: FnOnce(fn()) -> T,
 {
     STACK.with_borrow_mut(|stack| stack.push(Runtime::new()));
     f(|| {
-        STACK.with_borrow_mut(Vec::pop);
+        Runtime::with(|rt| rt.dispose_owned(rt.root));
+        let runtime = STACK
+            .with_borrow_mut(Vec::pop)
+            .expect("not within reactive runtime");
+        report_leaks(runtime);
     })
 }
 
+/// A reactive runtime which is only active within [`Root::enter`].
+///
+/// Unlike [`run`], this allows a runtime to be held across `await` points without leaving it active for
+/// unrelated code running on the same thread in the meantime. Everything owned by the runtime is disposed when
+/// the root is dropped.
+pub struct Root(Option<Runtime>);
+
+impl Root {
+    pub fn new() -> Self {
+        Self(Some(Runtime::new()))
+    }
+
+    /// Runs `f` with this root's runtime active.
+    pub fn enter<T, F>(&mut self, f: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        let runtime = self.0.take().expect("root entered recursively");
+        STACK.with_borrow_mut(|stack| stack.push(runtime));
+        let value = f();
+        self.0 = STACK.with_borrow_mut(Vec::pop);
+        value
+    }
+}
+
+impl Default for Root {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Root {
+    fn drop(&mut self) {
+        if self.0.is_some() {
+            self.enter(|| Runtime::with(|rt| rt.dispose_owned(rt.root)));
+            report_leaks(self.0.take().unwrap());
+        }
+    }
+}
+
+fn report_leaks(mut runtime: Runtime) {
+    let report = LeakReport::new(runtime.items.get_mut().drain().map(|(_, item)| item));
+    drop(runtime);
+    if !report.is_empty() {
+        report.emit();
+    }
+}
+
+/// Like [`std::panic::catch_unwind`], but also restores the state of the current runtime if `f` panics.
+///
+/// Items created by `f` before it panicked remain owned by the current scope.
+#[cfg(panic = "unwind")]
+pub fn catch_unwind<T, F>(f: F) -> std::thread::Result<T>
+where
+    F: FnOnce() -> T,
+{
+    let (scope, effect, tracking, batching, in_batch, flushing) = Runtime::with(|rt| {
+        (
+            rt.current_scope.get(),
+            rt.current_effect.borrow().clone(),
+            rt.tracking.get(),
+            rt.batching.get(),
+            rt.in_batch.get(),
+            rt.flushing.get(),
+        )
+    });
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+    if result.is_err() {
+        Runtime::with(|rt| {
+            rt.current_scope.set(scope);
+            rt.current_effect.replace(effect);
+            rt.tracking.set(tracking);
+            rt.batching.set(batching);
+            rt.in_batch.set(in_batch);
+            rt.flushing.set(flushing);
+        });
+    }
+    result
+}
+
 pub fn untrack<T, F>(f: F) -> T
 where
     F: FnOnce() -> T,
@@ -170,10 +351,12 @@ where
 {
     Runtime::with(|rt| {
         let prev = rt.batching.replace(true);
+        let prev_in_batch = rt.in_batch.replace(true);
         let value = f();
+        rt.in_batch.set(prev_in_batch);
         rt.batching.set(prev);
         if !prev {
-            rt.trigger_queued();
+            rt.schedule_flush();
         }
         value
     })
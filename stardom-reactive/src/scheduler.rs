@@ -0,0 +1,130 @@
+use std::{cell::Cell, rc::Rc};
+
+use crate::runtime::Runtime;
+
+/// The phase an effect is run in.
+///
+/// When queued effects are flushed, every effect in an earlier phase is run before any effect in a later phase.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+pub enum Phase {
+    /// Pure computations, such as [memos](crate::memo).
+    Compute,
+    /// Effects which write to the DOM.
+    #[default]
+    Render,
+    /// Effects which run after rendering, such as layout measurements.
+    Layout,
+}
+
+impl Phase {
+    pub(crate) const COUNT: usize = 3;
+
+    pub(crate) fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Decides when queued effects are flushed.
+///
+/// Whenever effects are queued outside of a [batch](crate::batch), the runtime calls [`Scheduler::schedule`]
+/// once, and waits for the provided [`Flush`] to be run or dropped before scheduling again. Memos are
+/// recomputed when read in the meantime.
+pub trait Scheduler {
+    fn schedule(&self, flush: Flush);
+}
+
+/// A pending flush of the effect queue, handed out to a [`Scheduler`].
+#[must_use = "queued effects are not run until the flush is run"]
+pub struct Flush {
+    cycle: u64,
+    pending: Rc<Cell<bool>>,
+}
+
+impl Flush {
+    pub(crate) fn new(cycle: u64, pending: Rc<Cell<bool>>) -> Self {
+        Self { cycle, pending }
+    }
+
+    /// Runs all queued effects.
+    ///
+    /// Does nothing if the runtime that requested the flush is no longer the active runtime.
+    pub fn run(self) {
+        Runtime::try_with(|rt| {
+            if rt.cycle == self.cycle {
+                rt.flush();
+            }
+        });
+    }
+}
+
+impl Drop for Flush {
+    fn drop(&mut self) {
+        // a flush which was never run lets the next queued effect schedule another
+        self.pending.set(false);
+    }
+}
+
+/// Sets the scheduler of the current runtime.
+pub fn set_scheduler<S>(scheduler: S)
+where
+    S: Scheduler + 'static,
+{
+    Runtime::with(|rt| {
+        rt.scheduler.replace(Rc::new(scheduler));
+    });
+}
+
+/// Immediately runs all queued effects, regardless of the current scheduler.
+pub fn flush() {
+    Runtime::with(Runtime::flush);
+}
+
+/// Flushes effects as soon as they are queued.
+///
+/// This is the default scheduler.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct SyncScheduler;
+
+impl Scheduler for SyncScheduler {
+    fn schedule(&self, flush: Flush) {
+        flush.run();
+    }
+}
+
+#[cfg(feature = "web")]
+pub use self::web::*;
+
+#[cfg(feature = "web")]
+mod web {
+    use wasm_bindgen::{prelude::*, JsCast};
+
+    use super::{Flush, Scheduler};
+
+    /// Flushes effects in a microtask, after the current task has finished.
+    #[derive(Clone, Copy, Default, Debug)]
+    pub struct MicrotaskScheduler;
+
+    impl Scheduler for MicrotaskScheduler {
+        fn schedule(&self, flush: Flush) {
+            let callback = Closure::once_into_js(move || flush.run());
+            window().queue_microtask(callback.unchecked_ref());
+        }
+    }
+
+    /// Flushes effects before the next repaint, coalescing all updates made within a frame.
+    #[derive(Clone, Copy, Default, Debug)]
+    pub struct AnimationFrameScheduler;
+
+    impl Scheduler for AnimationFrameScheduler {
+        fn schedule(&self, flush: Flush) {
+            let callback = Closure::once_into_js(move |_: f64| flush.run());
+            window()
+                .request_animation_frame(callback.unchecked_ref())
+                .unwrap();
+        }
+    }
+
+    fn window() -> web_sys::Window {
+        web_sys::window().expect("JavaScript window undefined")
+    }
+}
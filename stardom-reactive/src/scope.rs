@@ -1,20 +1,23 @@
-use std::collections::HashSet;
-
-use crate::runtime::{Handle, Runtime};
+use crate::{
+    diagnostics::{ItemKind, Stats},
+    runtime::{Handle, Runtime},
+};
 
 pub struct Scope {
     handle: Handle,
 }
 
 impl Default for Scope {
+    #[track_caller]
     fn default() -> Self {
         Self {
-            handle: Handle::scoped(),
+            handle: Handle::scoped(ItemKind::Scope),
         }
     }
 }
 
 impl Scope {
+    #[track_caller]
     pub fn new() -> Self {
         Self::default()
     }
@@ -31,19 +34,22 @@ impl Scope {
         })
     }
 
+    /// Returns the number of live items owned by this scope, including those owned indirectly.
+    pub fn stats(&self) -> Stats {
+        self.handle.with(|rt| {
+            let mut stats = Stats::default();
+            count_owned(rt, self.handle, &mut stats);
+            stats
+        })
+    }
+
     fn try_dispose(&self) {
-        self.handle
-            .try_with(|rt| {
-                if let Some(handles) = rt
-                    .scopes
-                    .try_borrow_mut()
-                    .ok()
-                    .and_then(|mut scopes| scopes.remove(&self.handle))
-                {
-                    dispose_set(rt, handles);
-                }
-            })
-            .ok();
+        // If the runtime is gone, everything owned by this scope went with it, and was reported by the
+        // runtime's leak report.
+        self.handle.try_with(|rt| {
+            rt.dispose_owned(self.handle);
+            rt.items.borrow_mut().remove(&self.handle);
+        });
     }
 }
 
@@ -53,6 +59,7 @@ impl Drop for Scope {
     }
 }
 
+#[track_caller]
 pub fn with_scope<T, F>(f: F) -> T
 where
     F: FnOnce() -> T,
@@ -60,12 +67,12 @@ where
     Scope::new().run(f)
 }
 
-fn dispose_set(rt: &Runtime, handles: HashSet<Handle>) {
-    for handle in handles {
-        if let Some(scope) = rt.scopes.borrow_mut().remove(&handle) {
-            dispose_set(rt, scope);
+fn count_owned(rt: &Runtime, owner: Handle, stats: &mut Stats) {
+    let owned = rt.scopes.borrow().get(&owner).cloned().unwrap_or_default();
+    for handle in owned {
+        if let Some(item) = rt.items.borrow().get(&handle) {
+            stats.add(item.kind);
         }
-
-        rt.signals.borrow_mut().remove(&handle);
+        count_owned(rt, handle, stats);
     }
 }
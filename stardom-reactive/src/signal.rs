@@ -8,10 +8,13 @@ use std::{
 
 use indexmap::IndexMap;
 
-use crate::{effect::Effect, runtime::Handle, Input, Output, Track, Trigger};
+use crate::{
+    diagnostics::ItemKind, effect::Effect, runtime::Handle, Input, Output, Track, Trigger,
+};
 
+#[track_caller]
 pub fn signal<T: 'static>(value: T) -> Signal<T> {
-    Signal::new(Handle::scoped(), value)
+    Signal::new(Handle::scoped(ItemKind::Signal), value)
 }
 
 pub struct Signal<T> {
@@ -28,6 +31,13 @@ impl<T: 'static> Signal<T> {
             _phantom: PhantomData,
         }
     }
+
+    /// Returns `true` if the signal has been disposed, or its runtime is no longer active.
+    pub fn is_disposed(&self) -> bool {
+        self.handle
+            .try_with(|rt| !rt.signals.borrow().contains_key(&self.handle))
+            .unwrap_or(true)
+    }
 }
 
 impl<T> Track for Signal<T> {
@@ -52,12 +62,9 @@ impl<T> Trigger for Signal<T> {
     fn trigger(&self) {
         self.handle.with(|rt| {
             let deps = mem::take(&mut *self.handle.signal().deps.borrow_mut());
-            if rt.batching.get() {
-                rt.effect_queue.borrow_mut().extend(deps);
-            } else {
-                for effect in deps.values() {
-                    effect.run();
-                }
+            rt.enqueue(deps);
+            if !rt.batching.get() {
+                rt.schedule_flush();
             }
         })
     }
@@ -4,9 +4,14 @@ extern crate self as stardom;
 
 pub use stardom_core::{
     self as core,
-    browser::mount,
-    hydrate::hydrate,
-    render::{render, render_to_string},
+    browser::{mount, mount_with, MountOptions},
+    dom, head,
+    hydrate::{hydrate, hydrate_with, HydrateOptions, HydrationError},
+    render::{
+        render, render_async, render_document, render_page, render_page_async, render_stream,
+        render_to_async_write, render_to_string, render_to_string_async, render_xml_document,
+    },
+    router, state,
     util::{document, window},
     IntoNode, Node,
 };
@@ -15,13 +20,16 @@ pub use stardom_reactive as reactive;
 pub mod prelude {
     pub use stardom_core::{
         component::{on_mount, on_unmount},
+        error::error_boundary,
+        flow::{client_only, each, portal, server_only, show, switch},
         named::elements::*,
+        suspense::{resource, suspense},
         Node, NodeRef,
     };
-    pub use stardom_macros::{component, element, fragment};
+    pub use stardom_macros::{component, element, fragment, IntoAttr};
     pub use stardom_reactive::{
-        batch, effect, lazy_effect, memo, signal, untrack, Input as _, Output as _, Track as _,
-        Trigger as _,
+        batch, effect, layout_effect, lazy_effect, memo, signal, untrack, Input as _, Output as _,
+        Track as _, Trigger as _,
     };
 
     // Hidden for macros